@@ -0,0 +1,76 @@
+//! compares checking a new edge segment against every drawn segment with
+//! looking up nearby segments in the grid first
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use sdl2::rect::Point;
+use sprouts_rs::view::spatial::SegmentGrid;
+use sprouts_rs::view::utils::{segment_intersection, Intersection};
+
+/// freehand looking strokes across an 800x600 board, made of short segments
+/// like the ones recorded from mouse motion
+fn random_strokes(segment_count: usize) -> Vec<(Point, Point)> {
+    // small lcg so the board is the same on every run
+    let mut seed: u32 = 12345;
+    let mut next = |range: i32| {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        return (seed >> 16) as i32 % range;
+    };
+    let mut segments = Vec::with_capacity(segment_count);
+    let mut p = Point::new(400, 300);
+    for i in 0..segment_count {
+        if i % 50 == 0 {
+            p = Point::new(next(800), next(600));
+        }
+        let q = Point::new(
+            (p.x + next(21) - 10).clamp(0, 799),
+            (p.y + next(21) - 10).clamp(0, 599),
+        );
+        segments.push((p, q));
+        p = q;
+    }
+    return segments;
+}
+
+fn bench_segment_checks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("check segment");
+    let (a, b) = (Point::new(400, 300), Point::new(408, 306));
+    for segment_count in [1_000, 10_000, 100_000] {
+        let segments = random_strokes(segment_count);
+        let mut grid = SegmentGrid::new(40);
+        for (p, q) in segments.iter() {
+            grid.insert(*p, *q);
+        }
+
+        group.bench_with_input(
+            BenchmarkId::new("linear", segment_count),
+            &segments,
+            |bench, segments| {
+                bench.iter(|| {
+                    segments
+                        .iter()
+                        .filter(|(p, q)| {
+                            segment_intersection(p, q, black_box(&a), black_box(&b))
+                                != Intersection::None
+                        })
+                        .count()
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("grid", segment_count),
+            &grid,
+            |bench, grid| {
+                bench.iter(|| {
+                    grid.query(black_box(&a), black_box(&b))
+                        .into_iter()
+                        .filter(|(p, q)| segment_intersection(p, q, &a, &b) != Intersection::None)
+                        .count()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_segment_checks);
+criterion_main!(benches);
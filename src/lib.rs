@@ -0,0 +1,7 @@
+//! the rules of Sprouts, and with the `sdl` feature the board that draws and
+//! plays them. `logic` doesn't depend on SDL, so other tools can use the rules
+//! on their own with `default-features = false`
+
+pub mod logic;
+#[cfg(feature = "sdl")]
+pub mod view;
@@ -1,3 +1,5 @@
-pub mod graph;
+pub mod canonical;
+pub mod export;
 pub mod game;
-
+pub mod graph;
+pub mod planar;
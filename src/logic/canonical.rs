@@ -0,0 +1,267 @@
+//! canonical forms of positions, so that positions that only differ by how the
+//! spots are numbered, by being mirrored, or by which region is drawn on the
+//! outside all collapse to the same code. Sprouts plays the same on a sphere as
+//! on the plane, so the outside region isn't treated as special.
+//!
+//! A position is a tree of regions and the connected pieces of the drawing
+//! that bound them. Each piece is coded by walking its darts from a starting dart
+//! on the boundary it was reached through, trying every starting dart and
+//! keeping the smallest code; the pieces in each region are sorted. Every
+//! region is tried as the root, with the drawing both ways round
+
+use crate::logic::graph::Dart;
+use crate::logic::planar::PlanarMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// a code that is the same for equivalent positions, along with a numbering
+/// of the spots that matches them up between equivalent positions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CanonicalForm {
+    pub code: String,
+    pub labels: BTreeMap<usize, usize>,
+}
+
+/// a code and the spots in the order they were reached while building it
+type Coded = (String, Vec<usize>);
+
+/// the parts of a planar map that the code is built from, possibly mirrored
+struct Position {
+    // the darts leaving each node, in order round it
+    rotations: HashMap<usize, Vec<Dart>>,
+    // the node each dart leaves and where it comes in that node's rotation
+    places: HashMap<Dart, (usize, usize)>,
+    // the boundary walks of each face, and the nodes inside it with no edges
+    faces: Vec<(Vec<Vec<Dart>>, Vec<usize>)>,
+    // the face and boundary each dart is part of
+    walks: HashMap<Dart, (usize, usize)>,
+}
+
+fn twin(dart: Dart) -> Dart {
+    return (dart.0, !dart.1);
+}
+
+impl Position {
+    fn new(map: &PlanarMap, mirrored: bool) -> Position {
+        let mut rotations = HashMap::new();
+        let mut places = HashMap::new();
+        for node in map.nodes() {
+            let mut rotation = map.rotation(node);
+            if mirrored {
+                rotation.reverse();
+            }
+            for (i, dart) in rotation.iter().enumerate() {
+                places.insert(*dart, (node, i));
+            }
+            rotations.insert(node, rotation);
+        }
+        let mut faces = Vec::new();
+        let mut walks = HashMap::new();
+        for face in 0..map.face_count() {
+            let mut boundaries = map.face_boundaries(face);
+            if mirrored {
+                // the mirror image of a face is to the left of the way back round it
+                for boundary in boundaries.iter_mut() {
+                    *boundary = boundary.iter().rev().map(|d| twin(*d)).collect();
+                }
+            }
+            for (i, boundary) in boundaries.iter().enumerate() {
+                for dart in boundary.iter() {
+                    walks.insert(*dart, (face, i));
+                }
+            }
+            faces.push((boundaries, map.isolated_nodes(face).to_vec()));
+        }
+        return Position {
+            rotations,
+            places,
+            faces,
+            walks,
+        };
+    }
+
+    /// codes everything in a face apart from the boundary it was reached through
+    fn code_face(&self, face: usize, from: Option<usize>) -> Coded {
+        let (boundaries, isolated) = &self.faces[face];
+        let mut parts = (0..boundaries.len())
+            .filter(|i| Some(*i) != from)
+            .map(|i| self.code_piece(face, i))
+            .collect::<Vec<_>>();
+        parts.extend(isolated.iter().map(|n| (String::from("."), vec![*n])));
+        parts.sort();
+        let mut code = String::from("{");
+        let mut nodes = Vec::new();
+        for (part, part_nodes) in parts {
+            code += &part;
+            nodes.extend(part_nodes);
+        }
+        code += "}";
+        return (code, nodes);
+    }
+
+    /// codes the connected piece of the drawing with the given boundary, and
+    /// everything in its other faces
+    fn code_piece(&self, face: usize, boundary: usize) -> Coded {
+        let walk = &self.faces[face].0[boundary];
+        // the other faces don't depend on where the walk starts, so are only coded once
+        let mut others = HashMap::new();
+        let mut best: Option<Coded> = None;
+        for start in walk.iter() {
+            let (mut code, numbers, mut nodes) = self.walk_piece(*start);
+            // the piece's other boundaries, in the order they were first reached
+            let mut darts = numbers.iter().collect::<Vec<_>>();
+            darts.sort_unstable_by_key(|(_, n)| **n);
+            let mut seen = HashSet::from([(face, boundary)]);
+            for (dart, _) in darts {
+                let (f, b) = self.walks[dart];
+                if seen.insert((f, b)) {
+                    let (part, part_nodes) = others
+                        .entry((f, b))
+                        .or_insert_with(|| self.code_face(f, Some(b)));
+                    code += part;
+                    nodes.extend(part_nodes.iter());
+                }
+            }
+            code += "]";
+            if best.as_ref().is_none_or(|(c, _)| code < *c) {
+                best = Some((code, nodes));
+            }
+        }
+        return best.unwrap();
+    }
+
+    /// numbers the darts of a connected piece, going round each node in turn
+    /// in the order they are reached, starting with the node start leaves.
+    /// The code lists, for each node, its degree and the numbers of the twins of
+    /// its darts, which is enough to build the piece again
+    fn walk_piece(&self, start: Dart) -> (String, HashMap<Dart, usize>, Vec<usize>) {
+        let mut numbers = HashMap::from([(start, 0)]);
+        // the nodes reached so far, and the dart to start going round each one from
+        let mut nodes = vec![self.places[&start].0];
+        let mut entries = vec![start];
+        let mut code = String::from("[");
+        let mut k = 0;
+        while k < nodes.len() {
+            let rotation = &self.rotations[&nodes[k]];
+            let first = self.places[&entries[k]].1;
+            code += &format!("{}:", rotation.len());
+            for i in 0..rotation.len() {
+                let dart = rotation[(first + i) % rotation.len()];
+                for d in [dart, twin(dart)] {
+                    if !numbers.contains_key(&d) {
+                        numbers.insert(d, numbers.len());
+                        let node = self.places[&d].0;
+                        if !nodes.contains(&node) {
+                            nodes.push(node);
+                            entries.push(d);
+                        }
+                    }
+                }
+                code += &format!("{},", numbers[&twin(dart)]);
+            }
+            code += ";";
+            k += 1;
+        }
+        return (code, numbers, nodes);
+    }
+}
+
+/// the canonical form of the position drawn on a planar map
+pub fn canonical_form(map: &PlanarMap) -> CanonicalForm {
+    let mut best: Option<Coded> = None;
+    for mirrored in [false, true] {
+        let position = Position::new(map, mirrored);
+        for face in 0..position.faces.len() {
+            let coded = position.code_face(face, None);
+            if best.as_ref().is_none_or(|(c, _)| coded.0 < *c) {
+                best = Some(coded);
+            }
+        }
+    }
+    let (code, nodes) = best.unwrap();
+    let labels = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+    return CanonicalForm { code, labels };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::canonical_form;
+    use crate::logic::planar::{PlanarMap, OUTER_FACE};
+    use std::f32::consts::PI;
+
+    /// a loop from spot `at` through a new spot, with the spots in `inside` on
+    /// one side of it and the rest on the other. Angles are negated when mirrored
+    fn loop_map(spots: usize, at: usize, inside: &[usize], mirrored: bool) -> PlanarMap {
+        let flip = if mirrored { -1.0 } else { 1.0 };
+        let mut map = PlanarMap::new();
+        for n in 0..spots + 1 {
+            map.add_node(n, OUTER_FACE);
+        }
+        let contains = |_: &[(usize, bool)], node: usize| inside.contains(&node);
+        map.add_edge(
+            at,
+            -flip * PI / 4.0,
+            spots,
+            -flip * 3.0 * PI / 4.0,
+            contains,
+        );
+        map.add_edge(spots, flip * 3.0 * PI / 4.0, at, flip * PI / 4.0, contains);
+        return map;
+    }
+
+    #[test]
+    fn test_relabelling() {
+        // spot 0 or spot 2 looping round, with one spot inside
+        let a = canonical_form(&loop_map(3, 0, &[1], false));
+        let b = canonical_form(&loop_map(3, 2, &[0], false));
+        assert_eq!(a.code, b.code);
+        // the looping spots are matched up, as are the new spots on the loops
+        assert_eq!(a.labels[&0], b.labels[&2]);
+        assert_eq!(a.labels[&3], b.labels[&3]);
+        assert_eq!(a.labels.len(), 4);
+    }
+
+    #[test]
+    fn test_mirror_and_outside() {
+        let plain = canonical_form(&loop_map(3, 0, &[1], false));
+        assert_eq!(plain.code, canonical_form(&loop_map(3, 0, &[1], true)).code);
+        // one spot on each side of the loop is the same wherever the outside is
+        assert_eq!(
+            plain.code,
+            canonical_form(&loop_map(3, 0, &[2], false)).code
+        );
+        // but both on the same side isn't
+        assert_ne!(
+            plain.code,
+            canonical_form(&loop_map(3, 0, &[1, 2], false)).code
+        );
+    }
+
+    #[test]
+    fn test_triangles() {
+        // a triangle 0 1 2 with spots 3 and 4 joined to corners 0 and 1 from
+        // outside, or with spot 3 inside it
+        let build = |inside: bool, flip: f32| {
+            let mut map = PlanarMap::new();
+            for n in 0..5 {
+                map.add_node(n, OUTER_FACE);
+            }
+            // the walk round the inside of the triangle goes from 0 to 1,
+            // unless it is mirrored
+            let contains = |walk: &[(usize, bool)], node: usize| {
+                (inside && node == 3) == walk.contains(&(0, flip > 0.0))
+            };
+            map.add_edge(0, 0.0, 1, flip * PI, contains);
+            map.add_edge(1, flip * 3.0 * PI / 4.0, 2, flip * -PI / 4.0, contains);
+            map.add_edge(2, flip * -3.0 * PI / 4.0, 0, flip * PI / 4.0, contains);
+            let out = if inside { PI / 8.0 } else { -PI / 2.0 };
+            map.add_edge(0, flip * out, 3, flip * PI / 2.0, contains);
+            map.add_edge(1, flip * -PI / 2.0, 4, flip * PI / 2.0, contains);
+            return map;
+        };
+        let outside = canonical_form(&build(false, 1.0)).code;
+        assert_eq!(outside, canonical_form(&build(false, -1.0)).code);
+        let inside = canonical_form(&build(true, 1.0)).code;
+        assert_eq!(inside, canonical_form(&build(true, -1.0)).code);
+        assert_ne!(inside, outside);
+    }
+}
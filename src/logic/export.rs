@@ -0,0 +1,145 @@
+//! descriptions of a game's graph for looking at positions with other tools,
+//! as Graphviz DOT or, with the serde feature, JSON
+
+use crate::logic::graph::Graph;
+use crate::logic::planar::PlanarMap;
+
+/// a spot, how many edges it has and whether more can be added
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExportedNode {
+    pub id: usize,
+    pub degree: usize,
+    pub live: bool,
+    // the regions the spot touches, if they were asked for
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub regions: Option<Vec<usize>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ExportedEdge {
+    pub id: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// the adjacency structure of a graph, ready to be written out
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GraphExport {
+    pub nodes: Vec<ExportedNode>,
+    pub edges: Vec<ExportedEdge>,
+}
+
+impl GraphExport {
+    /// describes a graph, with the regions of the board each spot touches if
+    /// a map of them is given
+    pub fn new(graph: &Graph, map: Option<&PlanarMap>) -> GraphExport {
+        let nodes = graph
+            .iter_nodes()
+            .map(|id| ExportedNode {
+                id,
+                degree: graph.edge_count(id),
+                live: graph.edge_count(id) < 3,
+                regions: map.map(|m| m.faces_of(id)),
+            })
+            .collect();
+        let edges = graph
+            .iter_edges()
+            .map(|(id, start, end)| ExportedEdge { id, start, end })
+            .collect();
+        return GraphExport { nodes, edges };
+    }
+
+    /// a Graphviz graph, with dead spots filled in grey
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph sprouts {\n");
+        for node in self.nodes.iter() {
+            let mut attributes = format!("degree={}, live={}", node.degree, node.live);
+            if !node.live {
+                attributes += ", style=filled, fillcolor=grey";
+            }
+            if let Some(regions) = &node.regions {
+                let regions = regions.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+                attributes += &format!(", regions=\"{}\"", regions.join(" "));
+            }
+            dot += &format!("  {} [{}];\n", node.id, attributes);
+        }
+        for edge in self.edges.iter() {
+            dot += &format!("  {} -- {} [id={}];\n", edge.start, edge.end, edge.id);
+        }
+        dot += "}\n";
+        return dot;
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        // only plain numbers and lists, so this can't fail
+        return serde_json::to_string_pretty(self).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GraphExport;
+    use crate::logic::graph::Graph;
+    use crate::logic::planar::{PlanarMap, OUTER_FACE};
+
+    fn two_moves() -> Graph {
+        // three spots, then spot 0 joined to itself through spot 3 and to
+        // spot 1 through spot 4, leaving spot 2 on its own
+        let mut graph = Graph::new();
+        for _ in 0..4 {
+            graph.add_node(vec![]);
+        }
+        graph.add_edge(0, 3);
+        graph.add_edge(3, 0);
+        graph.add_node(vec![]);
+        graph.add_edge(0, 4);
+        graph.add_edge(4, 1);
+        return graph;
+    }
+
+    #[test]
+    fn test_to_dot() {
+        assert_eq!(
+            two_moves().to_dot(),
+            "graph sprouts {\n\
+             \x20 0 [degree=3, live=false, style=filled, fillcolor=grey];\n\
+             \x20 1 [degree=1, live=true];\n\
+             \x20 2 [degree=0, live=true];\n\
+             \x20 3 [degree=2, live=true];\n\
+             \x20 4 [degree=2, live=true];\n\
+             \x20 0 -- 3 [id=0];\n\
+             \x20 3 -- 0 [id=1];\n\
+             \x20 0 -- 4 [id=2];\n\
+             \x20 4 -- 1 [id=3];\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_regions() {
+        let mut map = PlanarMap::new();
+        for n in 0..5 {
+            map.add_node(n, OUTER_FACE);
+        }
+        let export = GraphExport::new(&two_moves(), Some(&map));
+        assert_eq!(export.nodes[2].regions, Some(vec![OUTER_FACE]));
+        assert!(export
+            .to_dot()
+            .contains("2 [degree=0, live=true, regions=\"0\"]"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&GraphExport::new(&two_moves(), None).to_json()).unwrap();
+        assert_eq!(json["nodes"][1]["degree"], 1);
+        assert_eq!(json["nodes"][0]["live"], false);
+        assert!(json["nodes"][0].get("regions").is_none());
+        assert_eq!(json["edges"][3]["start"], 4);
+    }
+}
@@ -1,10 +1,76 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::fmt;
 
-use crate::logic::graph::{Graph, GraphLoop};
+use crate::logic::canonical::{self, CanonicalForm};
+use crate::logic::export::{ExportedEdge, GraphExport};
+use crate::logic::graph::{Dart, Graph};
+use crate::logic::planar::{PlanarMap, OUTER_FACE};
 
 use log::warn;
-use sdl2::rect::Rect;
 
+/// reasons a move can be rejected, either by the rules or by the drawn edge
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MoveError {
+    /// the game has already finished
+    GameOver,
+    /// the spot doesn't exist
+    InvalidSpot(usize),
+    /// the spot already has 3 edges
+    DeadSpot(usize),
+    /// a spot can only be connected to itself if it has fewer than 2 edges
+    SelfLoop(usize),
+    /// the spots don't share a region of the board
+    DifferentRegions(usize, usize),
+    /// the edge crosses an existing edge
+    Crossing,
+    /// the edge passes through a spot it doesn't connect
+    PassesThroughSpot(usize),
+    /// the edge doesn't finish on a spot
+    NoEndSpot,
+    /// the edge is too short to be drawn
+    TooShort,
+    /// only spots with no edges can be removed
+    HasEdges(usize),
+    /// the edge doesn't exist
+    InvalidEdge(usize),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::GameOver => write!(f, "The game is over"),
+            MoveError::InvalidSpot(n) => write!(f, "Spot {} doesn't exist", n),
+            MoveError::DeadSpot(n) => write!(f, "Spot {} already has 3 edges", n),
+            MoveError::SelfLoop(n) => {
+                write!(f, "Spot {} has too many edges to connect to itself", n)
+            }
+            MoveError::DifferentRegions(a, b) => {
+                write!(f, "Spots {} and {} are in different regions", a, b)
+            }
+            MoveError::Crossing => write!(f, "Edges can't cross"),
+            MoveError::PassesThroughSpot(n) => write!(f, "Edges can't pass through spot {}", n),
+            MoveError::NoEndSpot => write!(f, "Edges must end on a spot"),
+            MoveError::TooShort => write!(f, "Edge is too short"),
+            MoveError::HasEdges(n) => write!(f, "Spot {} still has edges", n),
+            MoveError::InvalidEdge(e) => write!(f, "Edge {} doesn't exist", e),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// the directions, as angles in radians, that a drawn edge leaves each node it
+/// touches. `middle` holds the directions from the new node back towards the
+/// start, and on towards the end
+#[derive(Clone, Copy, Debug)]
+pub struct EdgeDirections {
+    pub start: f32,
+    pub middle: (f32, f32),
+    pub end: f32,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Player {
     id: u8,
     name: String,
@@ -16,209 +82,524 @@ impl Player {
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameState {
     ACTIVE,
     END,
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Game {
     graph: Graph,
+    // how the drawn edges divide up the board
+    map: PlanarMap,
+    // the graph's id for each of the map's edges. The game numbers edges as the
+    // map does, which renumbers them when one is removed; the graph never does
+    edge_ids: Vec<usize>,
     player1: Player,
     player2: Player,
     turn: u8,
     pub state: GameState,
     winner: u8,
+    // in a sandbox, turns aren't taken and the game never ends
+    sandbox: bool,
 }
 
 impl Game {
     pub fn new(player1: Player, player2: Player, n: usize) -> Game {
         let mut graph = Graph::new();
+        let mut map = PlanarMap::new();
 
         for _i in 0..n {
-            graph.add_node(vec![]);
+            let node = graph.add_node(vec![]);
+            map.add_node(node, OUTER_FACE);
         }
 
         let player1_id = player1.id.clone();
         let game = Game {
             graph,
+            map,
+            edge_ids: Vec::new(),
             player1,
             player2,
             turn: player1_id,
             state: GameState::ACTIVE,
             winner: 0,
+            sandbox: false,
         };
 
         return game;
     }
 
+    /// adds a spot with no edges, returning its id. `inside` is given each
+    /// boundary of a region and should return whether the spot is on the
+    /// region's side of it; see [`PlanarMap::locate`]
+    pub fn add_spot(&mut self, inside: impl Fn(&[(usize, bool)]) -> bool) -> usize {
+        let face = self.map.locate(inside);
+        let node = self.graph.add_node(vec![]);
+        self.map.add_node(node, face);
+        return node;
+    }
+
+    /// removes a spot, as long as it has no edges
+    pub fn remove_spot(&mut self, node: usize) -> Result<(), MoveError> {
+        if !self.graph.has_node(node) {
+            return Err(MoveError::InvalidSpot(node));
+        }
+        if self.graph.edge_count(node) > 0 {
+            return Err(MoveError::HasEdges(node));
+        }
+        self.graph.remove_node(node);
+        self.map.remove_node(node);
+        return Ok(());
+    }
+
+    /// joins a and b directly, without putting a new spot on the edge, for
+    /// setting up a position. Returns the edge's id
+    pub fn add_edge(
+        &mut self,
+        a: usize,
+        a_angle: f32,
+        b: usize,
+        b_angle: f32,
+        face_contains: impl Fn(&[(usize, bool)], usize) -> bool,
+    ) -> Result<usize, MoveError> {
+        self.can_connect_nodes(a, b)?;
+        if self.map.face_at(a, a_angle) != self.map.face_at(b, b_angle) {
+            return Err(MoveError::DifferentRegions(a, b));
+        }
+        self.edge_ids.push(self.graph.add_edge(a, b).unwrap());
+        return Ok(self.map.add_edge(a, a_angle, b, b_angle, face_contains));
+    }
+
+    /// removes an edge. Later edges move down to fill in its id, as they do
+    /// in the map; see [`PlanarMap::remove_edge`]
+    pub fn remove_edge(
+        &mut self,
+        edge: usize,
+        face_contains: impl Fn(&[(usize, bool)], usize) -> bool,
+    ) -> Result<(), MoveError> {
+        if edge >= self.edge_ids.len() {
+            return Err(MoveError::InvalidEdge(edge));
+        }
+        self.graph.remove_edge(self.edge_ids.remove(edge));
+        self.map.remove_edge(edge, face_contains);
+        return Ok(());
+    }
+
+    pub fn edge_ends(&self, edge: usize) -> (usize, usize) {
+        return self.map.edge_ends(edge);
+    }
+
+    /// turns sandbox mode on or off. Moves can be made in any order in a
+    /// sandbox, and the game doesn't end until it is turned off. If no moves are
+    /// left then, the player whose turn it is has lost
+    pub fn set_sandbox(&mut self, sandbox: bool) {
+        self.sandbox = sandbox;
+        if !sandbox && self.check_endstate() {
+            self.winner = self.other_player(self.turn);
+            self.state = GameState::END;
+            println!("Game Over. Player {} won", self.winner);
+        } else {
+            self.state = GameState::ACTIVE;
+        }
+    }
+
+    /// a code shared by every position that is the same as this one up to
+    /// renumbering the spots or mirroring the board
+    pub fn canonical_form(&self) -> CanonicalForm {
+        return canonical::canonical_form(&self.map);
+    }
+
+    /// the graph ready to be written out, optionally with the regions each spot
+    /// touches. Edges are numbered the way the game numbers them, as in
+    /// [`Game::edge_ends`], which differs from [`Graph::to_dot`] once an edge
+    /// has been removed
+    pub fn export(&self, regions: bool) -> GraphExport {
+        let mut export = GraphExport::new(&self.graph, regions.then_some(&self.map));
+        // numbered the way the game numbers them, rather than by the graph's ids
+        export.edges = self
+            .edge_ids
+            .iter()
+            .enumerate()
+            .map(|(id, e)| {
+                let (start, end) = self.graph.edge(*e).unwrap();
+                return ExportedEdge { id, start, end };
+            })
+            .collect();
+        return export;
+    }
+
+    /// a cycle basis of the drawing, as walks of (edge, forwards) pairs; see
+    /// [`Graph::cycle_basis`]
+    pub fn cycles(&self) -> Vec<Vec<Dart>> {
+        return self.to_game_darts(self.graph.cycle_basis());
+    }
+
+    /// the walk round each boundary of each region, with the region on its left,
+    /// traced through the graph in the order the map has the edges round each spot
+    pub fn region_walks(&self) -> Vec<Vec<Dart>> {
+        let rotation = self
+            .map
+            .nodes()
+            .into_iter()
+            .map(|node| {
+                let darts = self.map.rotation(node);
+                return (
+                    node,
+                    darts.iter().map(|(e, f)| (self.edge_ids[*e], *f)).collect(),
+                );
+            })
+            .collect();
+        // the map's rotations always list every dart once, at the spot it leaves
+        return self.to_game_darts(self.graph.faces(&rotation).unwrap());
+    }
+
+    /// numbers the darts of walks in the graph the way the game numbers edges
+    fn to_game_darts(&self, walks: Vec<Vec<Dart>>) -> Vec<Vec<Dart>> {
+        let ids = self
+            .edge_ids
+            .iter()
+            .enumerate()
+            .map(|(id, e)| (*e, id))
+            .collect::<HashMap<_, _>>();
+        return walks
+            .into_iter()
+            .map(|walk| walk.into_iter().map(|(e, f)| (ids[&e], f)).collect())
+            .collect();
+    }
+
     pub fn get_nodes(&self) -> Vec<usize> {
-        return Vec::from_iter(self.graph.iter_nodes().cloned());
+        return self.graph.iter_nodes().collect();
     }
 
-    pub fn do_turn(&mut self, a: usize, b: usize) -> Option<usize> {
+    /// connects a and b, returning the id of the node placed on the new edge.
+    /// `directions` says which way the drawn edge leaves each node it touches,
+    /// and `face_contains` is used when the edge splits a region in two; see
+    /// [`PlanarMap::add_edge`]
+    pub fn do_turn(
+        &mut self,
+        a: usize,
+        b: usize,
+        directions: EdgeDirections,
+        face_contains: impl Fn(&[(usize, bool)], usize) -> bool,
+    ) -> Result<usize, MoveError> {
+        if !matches!(self.state, GameState::ACTIVE) {
+            return Err(MoveError::GameOver);
+        }
         // check if valid turn
-        if !self.can_connect_nodes(a, b) {
-            return None;
+        self.can_connect_nodes(a, b)?;
+        let face = self.map.face_at(a, directions.start);
+        if face != self.map.face_at(b, directions.end) {
+            return Err(MoveError::DifferentRegions(a, b));
         }
-        return Some(self.connect_nodes(a, b));
+        // add an edge between two nodes and put a node in the middle. The graph's
+        // edges go the same way as the map's, from a to the new node and on to b
+        let new_node = self.graph.add_node(vec![]);
+        self.edge_ids
+            .push(self.graph.add_edge(a, new_node).unwrap());
+        self.edge_ids
+            .push(self.graph.add_edge(new_node, b).unwrap());
+        self.map.add_node(new_node, face);
+        self.map.add_edge(
+            a,
+            directions.start,
+            new_node,
+            directions.middle.0,
+            &face_contains,
+        );
+        self.map.add_edge(
+            new_node,
+            directions.middle.1,
+            b,
+            directions.end,
+            &face_contains,
+        );
+        return Ok(new_node);
     }
 
-    pub fn end_turn(
-        &mut self,
-        point_in_polygon: impl Fn(usize, Vec<usize>) -> bool,
-        loop_size: impl Fn(Vec<usize>) -> Rect,
-    ) {
-        if self.check_endstate(point_in_polygon, loop_size) {
+    /// whether the edges could be redrawn leaving their ends in these directions,
+    /// given as (start, end) angles for each edge, without changing the order
+    /// they go round each spot in, and so without changing the regions
+    pub fn keeps_edge_order(&self, directions: &[(f32, f32)]) -> bool {
+        return self.map.keeps_rotations(directions);
+    }
+
+    /// records the new directions an edge leaves its ends in, when the board has
+    /// been redrawn without changing its regions
+    pub fn set_edge_directions(&mut self, edge: usize, start: f32, end: f32) {
+        self.map.set_angles(edge, start, end);
+    }
+
+    pub fn end_turn(&mut self) {
+        if self.sandbox {
+            return;
+        }
+        if self.check_endstate() {
             self.winner = self.turn;
             self.state = GameState::END;
             println!("Game Over. Player {} won", self.winner);
         } else {
-            if self.turn == self.player1.id {
-                self.turn = self.player2.id;
-            } else {
-                self.turn = self.player1.id;
-            }
+            self.turn = self.other_player(self.turn);
         }
     }
 
+    fn other_player(&self, player: u8) -> u8 {
+        if player == self.player1.id {
+            return self.player2.id;
+        }
+        return self.player1.id;
+    }
+
     pub fn is_node_alive(&self, node: usize) -> bool {
         return self.graph.edge_count(node) < 3;
     }
 
-    fn can_connect_nodes(&self, a: usize, b: usize) -> bool {
-        if !self.graph.has_node(a) || !self.graph.has_node(b) {
-            warn!("Invalid node passed to can_connect");
-            return false;
+    /// finds every node that `node` could legally be connected to this turn:
+    /// both must be alive and lie in a common region of the board
+    pub fn legal_partners(&self, node: usize) -> Vec<usize> {
+        let mut partners = self
+            .map
+            .faces_of(node)
+            .into_iter()
+            .flat_map(|face| self.map.nodes_on_face(face))
+            .filter(|&n| self.can_connect_nodes(node, n).is_ok())
+            .collect::<Vec<_>>();
+        partners.sort_unstable();
+        partners.dedup();
+        return partners;
+    }
+
+    fn can_connect_nodes(&self, a: usize, b: usize) -> Result<(), MoveError> {
+        for n in [a, b] {
+            if !self.graph.has_node(n) {
+                warn!("Invalid node passed to can_connect");
+                return Err(MoveError::InvalidSpot(n));
+            }
+        }
+        for n in [a, b] {
+            if !self.is_node_alive(n) {
+                return Err(MoveError::DeadSpot(n));
+            }
         }
         // can only connect to self if have 0 or 1 edges
         if a == b && self.graph.edge_count(a) >= 2 {
-            return false;
+            return Err(MoveError::SelfLoop(a));
         }
-        if !self.is_node_alive(a) || !self.is_node_alive(b) {
-            return false;
+        return Ok(());
+    }
+
+    /// the game is over when no region of the board has a legal move left in it
+    fn check_endstate(&self) -> bool {
+        for face in 0..self.map.face_count() {
+            let alive = self
+                .map
+                .nodes_on_face(face)
+                .into_iter()
+                .filter(|&n| self.is_node_alive(n))
+                .collect::<Vec<_>>();
+            // two live nodes can be joined, and one with 0 or 1 edges can be joined to itself
+            if alive.len() >= 2 || alive.iter().any(|&n| self.graph.edge_count(n) < 2) {
+                return false;
+            }
         }
         return true;
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EdgeDirections, Game, GameState, MoveError, Player};
+    use std::f32::consts::PI;
 
-    fn connect_nodes(&mut self, a: usize, b: usize) -> usize {
-        // add an edge between two nodes and put a node in the middle
-        return *self.graph.add_node(vec![a, b]);
+    fn one_spot_game() -> Game {
+        let player1 = Player::new(0, String::from("player 1"));
+        let player2 = Player::new(1, String::from("player 2"));
+        return Game::new(player1, player2, 1);
     }
 
-    fn check_endstate(
-        &self,
-        point_in_polygon: impl Fn(usize, Vec<usize>) -> bool,
-        loop_size: impl Fn(Vec<usize>) -> Rect,
-    ) -> bool {
-        // are there any nodes with 0 or 1 edges? These can at least connect to themselves
-        for n in self.graph.iter_nodes() {
-            let edge_count = self.graph.edge_count(*n);
-            if edge_count < 2 {
-                return false; // node could be connected to itself
-            }
-        }
+    // a loop from spot 0 round through a new spot to its right, and back.
+    // y points down the screen, so angles increase clockwise
+    const LOOP: EdgeDirections = EdgeDirections {
+        start: -PI / 4.0,
+        middle: (-3.0 * PI / 4.0, 3.0 * PI / 4.0),
+        end: PI / 4.0,
+    };
 
-        let polygons = self.get_loops();
-
-        let smallest_enclosing_polygon = |n: usize| {
-            let mut smallest_polygon: Option<&GraphLoop> = None;
-            let mut smallest_size: u32 = u32::MAX;
-            for polygon in polygons.iter() {
-                if polygon.contains(&n) {
-                    continue;
-                }
-                // TODO: the .to_vec()s below aren't great
-                println!("Point in polygon? {}", n);
-                if point_in_polygon(n, polygon.to_vec()) {
-                    let rect = loop_size(polygon.to_vec());
-                    let size = rect.width() * rect.height();
-                    if size < smallest_size {
-                        smallest_polygon = Some(polygon);
-                        smallest_size = size;
-                    }
-                }
-            }
-            println!("{:?}", smallest_polygon);
-            return smallest_polygon;
+    #[test]
+    fn test_one_spot_game() {
+        let mut game = one_spot_game();
+        let middle = game.do_turn(0, 0, LOOP, |_, _| false).unwrap();
+        game.end_turn();
+        assert!(matches!(game.state, GameState::ACTIVE));
+        // spot 0 now has two edges, so it can only be joined to the new spot
+        assert_eq!(game.legal_partners(0), vec![middle]);
+
+        // straight across the inside of the loop
+        let across = EdgeDirections {
+            start: 0.0,
+            middle: (PI, 0.0),
+            end: PI,
         };
+        game.do_turn(0, middle, across, |_, _| false).unwrap();
+        game.end_turn();
+        // the only live spot is the last one added, which can't loop round itself
+        assert!(matches!(game.state, GameState::END));
+        assert_eq!(game.winner, 1);
+    }
 
-        let active_nodes = Vec::from_iter(
-            self.graph
-                .iter_nodes()
-                .filter(|&&n| self.graph.edge_count(n) < 3),
-        );
-        for active_node in active_nodes.iter() {
-            println!("Active node: {}", active_node);
-            let sep = smallest_enclosing_polygon(**active_node);
-            println!("{:?}", sep);
-            if sep.is_some() {
-                for n in sep.unwrap().to_vec() {
-                    if self.graph.edge_count(n) < 3 {
-                        // node on smallest enclosing polygon is active therefore connectable
-                        return false;
-                    }
-                }
-            } else if active_nodes
-                .iter()
-                .filter(|&n| **n != **active_node)
-                .any(|&n| smallest_enclosing_polygon(*n).is_none())
-            {
-                // at least 2 active nodes on a non-enclosed polygon (on the outside)
-                return false;
-            }
+    #[test]
+    fn test_add_spot() {
+        let mut game = one_spot_game();
+        let spot = game.add_spot(|_| true);
+        assert_eq!(spot, 1);
+        assert_eq!(game.legal_partners(0), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_sandbox() {
+        let mut game = one_spot_game();
+        game.set_sandbox(true);
+        let middle = game.do_turn(0, 0, LOOP, |_, _| false).unwrap();
+        game.end_turn();
+        let across = EdgeDirections {
+            start: 0.0,
+            middle: (PI, 0.0),
+            end: PI,
+        };
+        game.do_turn(0, middle, across, |_, _| false).unwrap();
+        game.end_turn();
+        // the same moves as test_one_spot_game, but the game carries on
+        assert!(matches!(game.state, GameState::ACTIVE));
+        assert_eq!(game.remove_spot(0), Err(MoveError::HasEdges(0)));
+
+        // taking away the first half of the loop brings spot 0 back to life
+        game.remove_edge(0, |_, _| false).unwrap();
+        assert_eq!(game.edge_ends(0), (middle, 0));
+        assert!(game.is_node_alive(0));
+        game.set_sandbox(false);
+        assert!(matches!(game.state, GameState::ACTIVE));
+    }
+
+    #[test]
+    fn test_sandbox_ends_game() {
+        let mut game = one_spot_game();
+        game.set_sandbox(true);
+        // a loop with no spot on it leaves spot 0 with nothing to join to
+        game.add_edge(0, 0.0, 0, PI, |_, _| false).unwrap();
+        assert!(matches!(game.state, GameState::ACTIVE));
+        // player 0 still has the turn, and can't move
+        game.set_sandbox(false);
+        assert!(matches!(game.state, GameState::END));
+        assert_eq!(game.winner, 1);
+    }
+
+    #[test]
+    fn test_export_matches_map() {
+        let mut game = one_spot_game();
+        let middle = game.do_turn(0, 0, LOOP, |_, _| false).unwrap();
+        let edges = game.export(false).edges;
+        assert_eq!(edges.len(), 2);
+        for edge in edges.iter() {
+            assert_eq!(game.edge_ends(edge.id), (edge.start, edge.end));
         }
+        assert_eq!(game.edge_ends(0), (0, middle));
+    }
 
-        return true;
+    #[test]
+    fn test_remove_parallel_edge() {
+        let mut game = one_spot_game();
+        let other = game.add_spot(|_| true);
+        // two edges side by side from spot 0 to the other spot
+        game.add_edge(0, 0.0, other, PI, |_, _| false).unwrap();
+        game.add_edge(0, PI / 4.0, other, 3.0 * PI / 4.0, |_, _| false)
+            .unwrap();
+        // and one from the other spot back round to spot 0
+        game.add_edge(other, -PI / 2.0, 0, -PI / 2.0, |_, _| false)
+            .unwrap();
+        game.remove_edge(0, |_, _| false).unwrap();
+        assert_eq!(game.edge_ends(0), (0, other));
+        assert_eq!(game.edge_ends(1), (other, 0));
+        assert_eq!(
+            game.remove_edge(2, |_, _| false),
+            Err(MoveError::InvalidEdge(2))
+        );
+        let edges = game.export(false).edges;
+        assert_eq!(edges.len(), 2);
+        for edge in edges.iter() {
+            assert_eq!(game.edge_ends(edge.id), (edge.start, edge.end));
+        }
+        // removing the edge that was added last takes away that one, not
+        // another edge between the same spots
+        game.remove_edge(1, |_, _| false).unwrap();
+        assert_eq!(game.graph.iter_edges().count(), 1);
+        let (_, start, end) = game.graph.iter_edges().next().unwrap();
+        assert_eq!((start, end), game.edge_ends(0));
     }
 
-    fn get_loops(&self) -> Vec<GraphLoop> {
-        let mut graph_loops: Vec<GraphLoop> = vec![];
-
-        // iterate over each node and find loops
-        for n in self.get_nodes().iter() {
-            println!("{}'s neighbours: {:?}", n, self.graph.neighbours(*n));
-            self.get_loops_recursive(
-                &mut graph_loops,
-                &mut GraphLoop::from(vec![*n]),
-                &mut HashSet::new(),
-            );
-        }
-
-        // remove duplicates
-        let unique_graph_loops: HashSet<GraphLoop> = HashSet::from_iter(graph_loops);
-
-        // TODO: just for debugging. remove me
-        for p in unique_graph_loops.iter() {
-            println!("{:?}", p);
-        }
-
-        return unique_graph_loops.into_iter().collect::<Vec<GraphLoop>>();
-    }
-
-    fn get_loops_recursive(
-        &self,
-        loops: &mut Vec<GraphLoop>,
-        current_loop: &mut GraphLoop,
-        visited: &mut HashSet<usize>,
-    ) {
-        let current = *current_loop.last().unwrap();
-        println!("Visiting node {}", current);
-        visited.insert(current);
-        for neighbour in self.graph.neighbours(current) {
-            println!("Found neighbour {}", neighbour);
-            if !visited.contains(neighbour) {
-                let mut new_loop = current_loop.clone();
-                new_loop.push(*neighbour);
-                self.get_loops_recursive(loops, &mut new_loop, visited);
-            } else if *current_loop.first().unwrap() == *neighbour && current_loop.len() >= 3 {
-                // loop complete.
-                loops.push(current_loop.clone());
-                println!("Loop found: {:?}", current_loop);
-                break;
-            } else {
-                println!("Ignoring neighbour (already seen)");
-                continue;
-            }
+    #[test]
+    fn test_region_walks() {
+        let mut game = one_spot_game();
+        game.add_spot(|_| true);
+        let middle = game.do_turn(0, 0, LOOP, |_, _| false).unwrap();
+        game.end_turn();
+        // the loop is the only cycle, and it goes round through the middle spot
+        let cycles = game.cycles();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].len(), 2);
+        // one walk round each side of the loop, matching the map's regions
+        let mut walks = game.region_walks();
+        let mut boundaries = (0..game.map.face_count())
+            .flat_map(|face| game.map.face_boundaries(face))
+            .collect::<Vec<_>>();
+        assert_eq!(walks.len(), 2);
+        for walk in walks.iter_mut().chain(boundaries.iter_mut()) {
+            walk.sort_unstable();
         }
+        walks.sort_unstable();
+        boundaries.sort_unstable();
+        assert_eq!(walks, boundaries);
+        assert!(walks
+            .iter()
+            .all(|w| w.contains(&(0, true)) != w.contains(&(0, false))));
+        assert_eq!(game.edge_ends(1), (middle, 0));
+    }
+
+    #[test]
+    fn test_different_regions() {
+        let mut game = one_spot_game();
+        let middle = game.do_turn(0, 0, LOOP, |_, _| false).unwrap();
+        game.end_turn();
+        // starts inside the loop and ends outside it
+        let through_loop = EdgeDirections {
+            start: 0.0,
+            middle: (PI, 0.0),
+            end: 0.0,
+        };
+        assert_eq!(
+            game.do_turn(0, middle, through_loop, |_, _| false),
+            Err(MoveError::DifferentRegions(0, middle))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut game = one_spot_game();
+        let middle = game.do_turn(0, 0, LOOP, |_, _| false).unwrap();
+        game.end_turn();
+        let json = serde_json::to_string(&game).unwrap();
+        let mut copy: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.legal_partners(0), vec![middle]);
+        assert_eq!(copy.turn, game.turn);
+        // the copy's regions still work, so it can carry on being played
+        let across = EdgeDirections {
+            start: 0.0,
+            middle: (PI, 0.0),
+            end: PI,
+        };
+        copy.do_turn(0, middle, across, |_, _| false).unwrap();
+        copy.end_turn();
+        assert!(matches!(copy.state, GameState::END));
     }
 }
@@ -1,135 +1,415 @@
-use core::cmp::PartialEq;
-use core::hash::Hash;
-use std::collections::hash_map::{DefaultHasher, Keys};
-use std::collections::HashMap;
-use std::ops::{Deref, DerefMut};
+use crate::logic::export::GraphExport;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::vec::Vec;
 
+/// an edge followed one way: from its start to its end if forwards, or back again
+pub type Dart = (usize, bool);
+
+/// an undirected multigraph. Several edges can join the same two nodes, and an
+/// edge can join a node to itself. Nodes and edges keep their ids as others are
+/// added and removed, and ids aren't reused
 #[derive(Clone, Debug)]
-pub struct GraphLoop(Vec<usize>);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Graph {
+    // the edges at each node. A loop is listed twice, once for each end
+    adjacency_list: BTreeMap<usize, Vec<usize>>,
+    // the nodes at the ends of each edge
+    edges: BTreeMap<usize, (usize, usize)>,
+    next_node: usize,
+    next_edge: usize,
+}
 
-impl GraphLoop {
-    pub fn new() -> Self {
-        GraphLoop(vec![])
+impl Graph {
+    pub fn new() -> Graph {
+        Graph {
+            adjacency_list: BTreeMap::new(),
+            edges: BTreeMap::new(),
+            next_node: 0,
+            next_edge: 0,
+        }
     }
-}
 
-impl Deref for GraphLoop {
-    type Target = Vec<usize>;
-    fn deref(&self) -> &Vec<usize> {
-        &self.0
+    /// adds a node joined to each of the given nodes by a new edge, returning its id
+    pub fn add_node(&mut self, connections: Vec<usize>) -> usize {
+        let id = self.next_node;
+        self.next_node += 1;
+        self.adjacency_list.insert(id, Vec::new());
+        for conn in connections {
+            self.add_edge(id, conn);
+        }
+        return id;
     }
-}
 
-impl DerefMut for GraphLoop {
-    fn deref_mut(&mut self) -> &mut Vec<usize> {
-        &mut self.0
+    /// joins a and b, returning the new edge's id. None if either doesn't exist
+    pub fn add_edge(&mut self, a: usize, b: usize) -> Option<usize> {
+        if !self.has_node(a) || !self.has_node(b) {
+            return None;
+        }
+        let id = self.next_edge;
+        self.next_edge += 1;
+        self.edges.insert(id, (a, b));
+        self.adjacency_list.get_mut(&a).unwrap().push(id);
+        self.adjacency_list.get_mut(&b).unwrap().push(id);
+        return Some(id);
     }
-}
 
-impl From<Vec<usize>> for GraphLoop {
-    fn from(vec: Vec<usize>) -> Self {
-        return GraphLoop(vec);
+    /// removes an edge, returning its ends
+    pub fn remove_edge(&mut self, edge: usize) -> Option<(usize, usize)> {
+        let (a, b) = self.edges.remove(&edge)?;
+        for n in [a, b] {
+            let adjacent = self.adjacency_list.get_mut(&n).unwrap();
+            // only one end of a loop is taken away each time round
+            let i = adjacent.iter().position(|e| *e == edge).unwrap();
+            adjacent.remove(i);
+        }
+        return Some((a, b));
     }
-}
 
-impl PartialEq for GraphLoop {
-    fn eq(&self, other: &Self) -> bool {
-        let mut hasher1 = DefaultHasher::new();
-        let mut hasher2 = DefaultHasher::new();
-        return self.0.hash(&mut hasher1) == other.hash(&mut hasher2);
+    /// removes the most recently added edge between a and b, returning its id
+    pub fn remove_edge_between(&mut self, a: usize, b: usize) -> Option<usize> {
+        let edge = self
+            .edges_at(a)
+            .filter(|e| self.other_end(*e, a) == Some(b))
+            .max()?;
+        self.remove_edge(edge);
+        return Some(edge);
+    }
+
+    /// removes a node along with all its edges
+    pub fn remove_node(&mut self, node: usize) {
+        let edges = match self.adjacency_list.get(&node) {
+            Some(edges) => edges.clone(),
+            None => return,
+        };
+        for edge in edges {
+            self.remove_edge(edge);
+        }
+        self.adjacency_list.remove(&node);
+    }
+
+    /// the nodes at the ends of an edge
+    pub fn edge(&self, edge: usize) -> Option<(usize, usize)> {
+        return self.edges.get(&edge).copied();
     }
-}
 
-impl Eq for GraphLoop {}
-
-// TODO: make this more efficient through caching/
-// make this class a proper wrapper and only calculate when the vector is modified
-// This is massively over-complicated
-impl Hash for GraphLoop {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        let (mut i, n) = (0i8..)
-            .zip(self.0.iter())
-            .min_by(|(_, a), (_, b)| a.cmp(b))
-            .unwrap();
-        let dir: i8;
-        // ensure consistent equality in either direction by hashing in the 'upwards' direction around the min point
-        if self.0.len() > 2 {
-            let right = self.0[((i + 1) % (self.0.len() as i8)) as usize];
-            let left: usize;
-            if i - 1 < 0 {
-                left = *self.0.last().unwrap();
-            } else {
-                left = self.0[i as usize - 1];
+    /// the node at the other end of an edge from node. None if the edge isn't at node
+    pub fn other_end(&self, edge: usize, node: usize) -> Option<usize> {
+        return match self.edge(edge)? {
+            (a, b) if a == node => Some(b),
+            (a, b) if b == node => Some(a),
+            _ => None,
+        };
+    }
+
+    /// the ids of the edges at a node. Loops come up twice
+    pub fn edges_at(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        return self.adjacency_list[&node].iter().copied();
+    }
+
+    /// the nodes joined to node, once for each edge joining them
+    pub fn neighbours(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        return self
+            .edges_at(node)
+            .map(move |e| self.other_end(e, node).unwrap());
+    }
+
+    /// the degree of a node, where a loop counts twice
+    pub fn edge_count(&self, node: usize) -> usize {
+        return self.adjacency_list.get(&node).unwrap().len();
+    }
+
+    /// every node id, in increasing order
+    pub fn iter_nodes(&self) -> impl Iterator<Item = usize> + '_ {
+        return self.adjacency_list.keys().copied();
+    }
+
+    /// every edge as (id, start, end), in increasing order of id
+    pub fn iter_edges(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        return self.edges.iter().map(|(id, (a, b))| (*id, *a, *b));
+    }
+
+    pub fn node_count(&self) -> usize {
+        return self.adjacency_list.len();
+    }
+
+    pub fn total_edges(&self) -> usize {
+        return self.edges.len();
+    }
+
+    pub fn has_node(&self, node: usize) -> bool {
+        return self.adjacency_list.contains_key(&node);
+    }
+
+    /// the graph in Graphviz DOT, with each node's degree and whether it is live.
+    /// Edges are labelled with the graph's own ids, which stay the same when
+    /// other edges are removed; [`Game::export`](crate::logic::game::Game::export)
+    /// numbers them the way the game and the board do instead
+    pub fn to_dot(&self) -> String {
+        return GraphExport::new(self, None).to_dot();
+    }
+
+    /// the node a dart leaves from
+    pub fn tail(&self, dart: Dart) -> usize {
+        let (a, b) = self.edges[&dart.0];
+        return if dart.1 { a } else { b };
+    }
+
+    /// the node a dart arrives at
+    pub fn head(&self, dart: Dart) -> usize {
+        return self.tail((dart.0, !dart.1));
+    }
+
+    /// a fundamental cycle basis: one cycle for each edge left out of a
+    /// breadth-first spanning forest, closed up through the tree. Each cycle is
+    /// the darts of a closed walk, starting with that edge followed forwards.
+    /// There are E - V + C of them for C connected components, and finding them
+    /// takes O(V + E) time plus the length of the cycles, which is O(E * V) at worst
+    pub fn cycle_basis(&self) -> Vec<Vec<Dart>> {
+        // the dart from each node's parent in the forest down to it, and how deep it is
+        let mut parent: BTreeMap<usize, Option<Dart>> = BTreeMap::new();
+        let mut depth = HashMap::new();
+        let mut tree = BTreeSet::new();
+        for root in self.iter_nodes() {
+            if parent.contains_key(&root) {
+                continue;
             }
-            if right > left {
-                dir = 1
-            } else {
-                dir = -1;
+            parent.insert(root, None);
+            depth.insert(root, 0);
+            let mut queue = VecDeque::from([root]);
+            while let Some(n) = queue.pop_front() {
+                for edge in self.edges_at(n) {
+                    let m = self.other_end(edge, n).unwrap();
+                    if let Entry::Vacant(entry) = parent.entry(m) {
+                        entry.insert(Some((edge, self.edges[&edge].0 == n)));
+                        depth.insert(m, depth[&n] + 1);
+                        tree.insert(edge);
+                        queue.push_back(m);
+                    }
+                }
             }
-        } else {
-            dir = 1;
         }
-        let mut j = 0;
-        loop {
-            state.write_usize(self.0[i as usize]);
-            i = i + 1 * dir;
-            if i < 0 {
-                i = self.0.len() as i8 - 1;
-            } else if i > (self.0.len() as i8) - 1 {
-                i = 0;
+
+        let mut cycles = Vec::new();
+        for (edge, a, b) in self.iter_edges() {
+            if tree.contains(&edge) {
+                continue;
             }
-            j += 1;
-            if j == self.0.len() {
-                break;
+            // after the edge from a to b, climb the tree from b and from a until
+            // they meet, then come back down to a
+            let (mut up, mut down) = (Vec::new(), Vec::new());
+            let (mut x, mut y) = (b, a);
+            while x != y {
+                if depth[&x] >= depth[&y] {
+                    let dart = parent[&x].unwrap();
+                    up.push((dart.0, !dart.1));
+                    x = self.tail(dart);
+                } else {
+                    let dart = parent[&y].unwrap();
+                    down.push(dart);
+                    y = self.tail(dart);
+                }
             }
+            let mut cycle = vec![(edge, true)];
+            cycle.extend(up);
+            cycle.extend(down.into_iter().rev());
+            cycles.push(cycle);
         }
+        return cycles;
     }
-}
 
-#[derive(Debug)]
-pub struct Graph {
-    adjacency_list: HashMap<usize, Vec<usize>>,
-}
-
-impl Graph {
-    pub fn new() -> Graph {
-        Graph {
-            adjacency_list: HashMap::new(),
+    /// the faces of the graph drawn in the plane, as closed walks of darts, each
+    /// with its face on the left. The drawing is given by `rotation`, listing the
+    /// darts leaving each node in order of increasing angle, using this graph's
+    /// edge ids. Each connected component with at least one edge is walked
+    /// separately, so one with V nodes and E edges has E - V + 2 walks,
+    /// including the one round its outside. Nodes with no edges aren't on any
+    /// walk. Takes O(V + E) time. Fails unless every dart is listed exactly once,
+    /// at the node it leaves
+    pub fn faces(&self, rotation: &BTreeMap<usize, Vec<Dart>>) -> Result<Vec<Vec<Dart>>, String> {
+        // where each dart comes in the rotation at its tail
+        let mut position = HashMap::new();
+        for (node, darts) in rotation.iter() {
+            for (i, dart) in darts.iter().enumerate() {
+                if self.edge(dart.0).map(|_| self.tail(*dart)) != Some(*node) {
+                    return Err(format!("dart {:?} doesn't leave {}", dart, node));
+                }
+                if position.insert(*dart, i).is_some() {
+                    return Err(format!("dart {:?} is listed twice", dart));
+                }
+            }
         }
-    }
+        if position.len() != 2 * self.total_edges() {
+            return Err(String::from("not every dart is listed"));
+        }
+        // arriving at a node along a dart, the walk leaves along the dart before
+        // the way back, keeping the face on its left
+        let next = |dart: Dart| {
+            let back = (dart.0, !dart.1);
+            let darts = &rotation[&self.tail(back)];
+            return darts[(position[&back] + darts.len() - 1) % darts.len()];
+        };
 
-    pub fn add_node(&mut self, connections: Vec<usize>) -> &usize {
-        let id = self.adjacency_list.len();
-        for conn in &connections {
-            self.add_edge(id, *conn);
+        let mut faces = Vec::new();
+        let mut seen = HashSet::new();
+        for (edge, _, _) in self.iter_edges() {
+            for start in [(edge, true), (edge, false)] {
+                if seen.contains(&start) {
+                    continue;
+                }
+                let mut face = Vec::new();
+                let mut dart = start;
+                loop {
+                    seen.insert(dart);
+                    face.push(dart);
+                    dart = next(dart);
+                    if dart == start {
+                        break;
+                    }
+                }
+                faces.push(face);
+            }
         }
-        self.adjacency_list.insert(id, connections);
-        return self.adjacency_list.get_key_value(&id).unwrap().0;
+        return Ok(faces);
     }
+}
 
-    pub fn add_edge(&mut self, a: usize, b: usize) {
-        if let Some(a_adjacent) = self.adjacency_list.get_mut(&a) {
-            a_adjacent.push(b);
-        }
-        if let Some(b_adjacent) = self.adjacency_list.get_mut(&b) {
-            b_adjacent.push(a);
+#[cfg(test)]
+mod tests {
+    use super::{Dart, Graph};
+    use std::collections::BTreeMap;
+
+    /// checks each dart arrives where the next one leaves from
+    fn assert_closed(graph: &Graph, walk: &[Dart]) {
+        for (i, dart) in walk.iter().enumerate() {
+            assert_eq!(graph.head(*dart), graph.tail(walk[(i + 1) % walk.len()]));
         }
     }
 
-    pub fn neighbours(&self, node: usize) -> &Vec<usize> {
-        return self.adjacency_list.get(&node).unwrap();
+    #[test]
+    fn test_remove() {
+        let mut graph = Graph::new();
+        let a = graph.add_node(vec![]);
+        let b = graph.add_node(vec![a]);
+        let loop_edge = graph.add_edge(a, a).unwrap();
+        assert_eq!(graph.edge_count(a), 3);
+
+        // a loop counts twice, and removing it takes both ends away
+        assert_eq!(graph.remove_edge(loop_edge), Some((a, a)));
+        assert_eq!(graph.neighbours(a).collect::<Vec<_>>(), vec![b]);
+        assert_eq!(graph.remove_edge(loop_edge), None);
+
+        graph.remove_node(b);
+        assert!(!graph.has_node(b));
+        assert_eq!(graph.edge_count(a), 0);
+        assert_eq!(graph.total_edges(), 0);
+        // b's id isn't given to the next node
+        assert_eq!(graph.add_node(vec![]), 2);
+        assert_eq!(graph.iter_nodes().collect::<Vec<_>>(), vec![a, 2]);
     }
 
-    pub fn edge_count(&self, node: usize) -> usize {
-        return self.adjacency_list.get(&node).unwrap().len();
+    #[test]
+    fn test_multi_edges() {
+        let mut graph = Graph::new();
+        let a = graph.add_node(vec![]);
+        let b = graph.add_node(vec![a, a]);
+        let c = graph.add_node(vec![b]);
+        assert_eq!(graph.edge_count(a), 2);
+        assert_eq!(graph.neighbours(b).collect::<Vec<_>>(), vec![a, a, c]);
+        assert_eq!(
+            graph.iter_edges().collect::<Vec<_>>(),
+            vec![(0, b, a), (1, b, a), (2, c, b)]
+        );
+
+        // the two edges between a and b are told apart by their ids
+        assert_eq!(graph.remove_edge_between(a, b), Some(1));
+        assert_eq!(graph.edges_at(a).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(graph.other_end(0, a), Some(b));
+        assert_eq!(graph.other_end(2, a), None);
+        assert_eq!(graph.add_edge(a, 7), None);
+        assert_eq!(graph.node_count(), 3);
     }
 
-    pub fn iter_nodes(&self) -> Keys<'_, usize, Vec<usize>> {
-        return self.adjacency_list.keys();
+    #[test]
+    fn test_cycle_basis() {
+        // the complete graph on four nodes
+        let mut k4 = Graph::new();
+        for n in 0..4 {
+            k4.add_node((0..n).collect());
+        }
+        let cycles = k4.cycle_basis();
+        assert_eq!(cycles.len(), 6 - 4 + 1);
+        for cycle in cycles.iter() {
+            assert_closed(&k4, cycle);
+            assert_eq!(cycle.len(), 3);
+        }
+
+        // three edges between two nodes, a loop and a separate path
+        let mut graph = Graph::new();
+        let a = graph.add_node(vec![]);
+        let b = graph.add_node(vec![a, a, a]);
+        graph.add_edge(b, b);
+        let c = graph.add_node(vec![]);
+        graph.add_node(vec![c]);
+        let cycles = graph.cycle_basis();
+        assert_eq!(
+            cycles.iter().map(|c| c.len()).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+        for cycle in cycles.iter() {
+            assert_closed(&graph, cycle);
+        }
     }
 
-    pub fn has_node(&self, node: usize) -> bool {
-        return self.adjacency_list.contains_key(&node);
+    #[test]
+    fn test_faces() {
+        // a triangle a b c, with a at the origin, b to the right and c below.
+        // y points down the screen, so angles increase clockwise
+        let mut triangle = Graph::new();
+        let (a, b, c) = (
+            triangle.add_node(vec![]),
+            triangle.add_node(vec![]),
+            triangle.add_node(vec![]),
+        );
+        triangle.add_edge(a, b);
+        triangle.add_edge(b, c);
+        triangle.add_edge(c, a);
+        let rotation = BTreeMap::from([
+            (a, vec![(0, true), (2, false)]),
+            (b, vec![(1, true), (0, false)]),
+            (c, vec![(2, true), (1, false)]),
+        ]);
+        let faces = triangle.faces(&rotation).unwrap();
+        // the inside and the outside
+        assert_eq!(
+            faces,
+            vec![
+                vec![(0, true), (1, true), (2, true)],
+                vec![(0, false), (2, false), (1, false)]
+            ]
+        );
+
+        // two edges between two spots and a loop at one of them, as in sprouts
+        let mut graph = Graph::new();
+        let a = graph.add_node(vec![]);
+        let b = graph.add_node(vec![a, a]);
+        graph.add_edge(a, a);
+        let rotation = BTreeMap::from([
+            (a, vec![(0, false), (2, true), (2, false), (1, false)]),
+            (b, vec![(1, true), (0, true)]),
+        ]);
+        let faces = graph.faces(&rotation).unwrap();
+        assert_eq!(faces.len(), 3 - 2 + 2);
+        for face in faces.iter() {
+            assert_closed(&graph, face);
+        }
+        assert_eq!(faces.iter().map(|f| f.len()).sum::<usize>(), 2 * 3);
+
+        // a dart listed at the wrong end, or left out
+        let mut wrong = rotation.clone();
+        wrong.get_mut(&b).unwrap()[0] = (1, false);
+        assert!(graph.faces(&wrong).is_err());
+        wrong.get_mut(&b).unwrap().remove(0);
+        assert!(graph.faces(&wrong).is_err());
     }
 }
@@ -0,0 +1,557 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// the face every node starts in, before any edges are drawn
+pub const OUTER_FACE: usize = 0;
+
+/// one side of an edge. Half-edges 2e and 2e + 1 make up edge e, the first
+/// running from the edge's start to its end and the second running back.
+/// The face a half-edge belongs to lies on its left
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct HalfEdge {
+    origin: usize,
+    // direction the half-edge leaves its origin, in radians
+    angle: f32,
+    next: usize,
+    prev: usize,
+    face: usize,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Face {
+    // a half-edge from each separate boundary of the face
+    boundaries: Vec<usize>,
+    // nodes with no edges, lying inside the face
+    isolated: Vec<usize>,
+}
+
+/// a doubly-connected edge list describing how the drawn graph divides the
+/// board into faces (regions). It records the order edges leave each node in,
+/// so it can tell which face a new edge is drawn into, and keeps track of the
+/// boundaries of each face as edges are added
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlanarMap {
+    half_edges: Vec<HalfEdge>,
+    faces: Vec<Face>,
+    // half-edges leaving each node, sorted by angle
+    rotations: HashMap<usize, Vec<usize>>,
+}
+
+fn twin(half_edge: usize) -> usize {
+    return half_edge ^ 1;
+}
+
+impl Default for PlanarMap {
+    fn default() -> PlanarMap {
+        return PlanarMap::new();
+    }
+}
+
+impl PlanarMap {
+    pub fn new() -> PlanarMap {
+        PlanarMap {
+            half_edges: Vec::new(),
+            faces: vec![Face {
+                boundaries: Vec::new(),
+                isolated: Vec::new(),
+            }],
+            rotations: HashMap::new(),
+        }
+    }
+
+    /// adds a node with no edges inside the given face
+    pub fn add_node(&mut self, node: usize, face: usize) {
+        self.rotations.insert(node, Vec::new());
+        self.faces[face].isolated.push(node);
+    }
+
+    /// removes a node with no edges
+    pub fn remove_node(&mut self, node: usize) {
+        debug_assert!(self.rotations[&node].is_empty());
+        self.rotations.remove(&node);
+        for face in self.faces.iter_mut() {
+            face.isolated.retain(|n| *n != node);
+        }
+    }
+
+    /// the nodes at the start and end of an edge
+    pub fn edge_ends(&self, edge: usize) -> (usize, usize) {
+        return (
+            self.half_edges[2 * edge].origin,
+            self.half_edges[2 * edge + 1].origin,
+        );
+    }
+
+    pub fn edge_count(&self) -> usize {
+        return self.half_edges.len() / 2;
+    }
+
+    pub fn face_count(&self) -> usize {
+        return self.faces.len();
+    }
+
+    /// the face an edge leaving node at the given angle would be drawn into
+    pub fn face_at(&self, node: usize, angle: f32) -> usize {
+        return match self.corner(node, angle) {
+            Some(before) => self.half_edges[before].face,
+            None => self.isolated_face(node),
+        };
+    }
+
+    /// the face a point lies in. `inside` is given each boundary of a face and
+    /// should return whether the point is on the face's side of it
+    pub fn locate(&self, inside: impl Fn(&[(usize, bool)]) -> bool) -> usize {
+        // a face with no boundaries is the whole board, before any edges are drawn
+        return (0..self.faces.len())
+            .find(|face| self.face_boundaries(*face).iter().all(|b| inside(b)))
+            .unwrap_or(OUTER_FACE);
+    }
+
+    /// every face that node touches
+    pub fn faces_of(&self, node: usize) -> Vec<usize> {
+        let rotation = &self.rotations[&node];
+        if rotation.is_empty() {
+            return vec![self.isolated_face(node)];
+        }
+        let mut faces = rotation
+            .iter()
+            .map(|h| self.half_edges[*h].face)
+            .collect::<Vec<_>>();
+        faces.sort_unstable();
+        faces.dedup();
+        return faces;
+    }
+
+    /// every node, in increasing order
+    pub fn nodes(&self) -> Vec<usize> {
+        let mut nodes = self.rotations.keys().copied().collect::<Vec<_>>();
+        nodes.sort_unstable();
+        return nodes;
+    }
+
+    /// the edges leaving node in order of increasing angle, as (edge, forwards)
+    /// pairs, where forwards means the edge starts at node
+    pub fn rotation(&self, node: usize) -> Vec<(usize, bool)> {
+        return Self::as_edges(&self.rotations[&node]);
+    }
+
+    /// the nodes with no edges inside a face
+    pub fn isolated_nodes(&self, face: usize) -> &[usize] {
+        return &self.faces[face].isolated;
+    }
+
+    /// every node on the boundary of the face or inside it with no edges
+    pub fn nodes_on_face(&self, face: usize) -> Vec<usize> {
+        let mut nodes = self.faces[face].isolated.clone();
+        for boundary in self.faces[face].boundaries.iter() {
+            nodes.extend(
+                self.walk(*boundary)
+                    .iter()
+                    .map(|h| self.half_edges[*h].origin),
+            );
+        }
+        nodes.sort_unstable();
+        nodes.dedup();
+        return nodes;
+    }
+
+    /// the edges around each boundary of a face, as (edge, forwards) pairs,
+    /// where forwards means the edge is walked from its start to its end
+    pub fn face_boundaries(&self, face: usize) -> Vec<Vec<(usize, bool)>> {
+        return self.faces[face]
+            .boundaries
+            .iter()
+            .map(|h| Self::as_edges(&self.walk(*h)))
+            .collect();
+    }
+
+    /// adds an edge from a to b, returning its id. The angles are the
+    /// directions the edge leaves a and b in. Both ends have to be in the same face.
+    /// If the edge closes a loop, the face is split in two, and `face_contains` is
+    /// used to decide which of the two each of the old face's other parts is in.
+    /// It is given the boundary of the new face and a node, and should return
+    /// whether the node lies inside that face
+    pub fn add_edge(
+        &mut self,
+        a: usize,
+        a_angle: f32,
+        b: usize,
+        b_angle: f32,
+        face_contains: impl Fn(&[(usize, bool)], usize) -> bool,
+    ) -> usize {
+        let face = self.face_at(a, a_angle);
+        debug_assert_eq!(face, self.face_at(b, b_angle));
+
+        // find the boundaries of the face that a and b are on before they're joined up
+        let a_corner = self.corner(a, a_angle);
+        let b_corner = self.corner(b, b_angle);
+        let a_boundary = a_corner.map(|h| self.boundary_index(face, h));
+        let b_boundary = b_corner.map(|h| self.boundary_index(face, h));
+        let splits = a == b || (a_boundary.is_some() && a_boundary == b_boundary);
+
+        let edge = self.edge_count();
+        let (h, t) = (2 * edge, 2 * edge + 1);
+        for (origin, angle) in [(a, a_angle), (b, b_angle)] {
+            self.half_edges.push(HalfEdge {
+                origin,
+                angle,
+                next: 0,
+                prev: 0,
+                face,
+            });
+        }
+        self.insert_into_rotation(a, h);
+        self.insert_into_rotation(b, t);
+        self.relink(a);
+        if a != b {
+            self.relink(b);
+        }
+
+        let old_face = &mut self.faces[face];
+        old_face.isolated.retain(|n| *n != a && *n != b);
+        let mut removed = [a_boundary, b_boundary]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        removed.sort_unstable();
+        removed.dedup();
+        for i in removed.into_iter().rev() {
+            old_face.boundaries.remove(i);
+        }
+
+        if !splits {
+            // a and b were on separate parts of the face, which are now joined into one
+            old_face.boundaries.push(h);
+            return edge;
+        }
+
+        // the loop closed by the edge splits the face. The side to the left of h
+        // becomes a new face and the other side keeps the old one
+        old_face.boundaries.push(t);
+        let new_face = self.faces.len();
+        self.faces.push(Face {
+            boundaries: vec![h],
+            isolated: Vec::new(),
+        });
+        for half_edge in self.walk(h) {
+            self.half_edges[half_edge].face = new_face;
+        }
+
+        // anything else in the old face is now on one side of the loop or the other
+        let new_boundary = Self::as_edges(&self.walk(h));
+        let old_face = &mut self.faces[face];
+        let (boundaries, isolated) = (
+            std::mem::take(&mut old_face.boundaries),
+            std::mem::take(&mut old_face.isolated),
+        );
+        for boundary in boundaries {
+            let node = self.half_edges[boundary].origin;
+            let target = if boundary != t && face_contains(&new_boundary, node) {
+                for half_edge in self.walk(boundary) {
+                    self.half_edges[half_edge].face = new_face;
+                }
+                new_face
+            } else {
+                face
+            };
+            self.faces[target].boundaries.push(boundary);
+        }
+        for node in isolated {
+            let target = if face_contains(&new_boundary, node) {
+                new_face
+            } else {
+                face
+            };
+            self.faces[target].isolated.push(node);
+        }
+        return edge;
+    }
+
+    /// removes an edge, joining up the faces either side of it. Later edges
+    /// move down to fill in its id, so callers that keep ids of their own, like
+    /// [`Graph`](crate::logic::graph::Graph)'s stable ones, have to renumber to
+    /// match. The whole map is built again from the edges that are left, which
+    /// takes as long as adding them all, and `face_contains` is used as in
+    /// [`PlanarMap::add_edge`]
+    pub fn remove_edge(
+        &mut self,
+        edge: usize,
+        face_contains: impl Fn(&[(usize, bool)], usize) -> bool,
+    ) {
+        let edges = (0..self.edge_count())
+            .filter(|e| *e != edge)
+            .map(|e| {
+                (
+                    self.half_edges[2 * e].clone(),
+                    self.half_edges[2 * e + 1].clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut nodes = self.rotations.keys().copied().collect::<Vec<_>>();
+        nodes.sort_unstable();
+        *self = PlanarMap::new();
+        for node in nodes {
+            self.add_node(node, OUTER_FACE);
+        }
+        // each edge's ends were in one face when it was first added, and taking
+        // edges away only joins faces, so they still are
+        for (h, t) in edges {
+            self.add_edge(h.origin, h.angle, t.origin, t.angle, &face_contains);
+        }
+    }
+
+    /// whether giving every edge new directions, as (start, end) angles indexed
+    /// by edge, would keep the edges going round each node in the same order
+    pub fn keeps_rotations(&self, angles: &[(f32, f32)]) -> bool {
+        let angle = |h: usize| {
+            return if h.is_multiple_of(2) {
+                angles[h / 2].0
+            } else {
+                angles[h / 2].1
+            };
+        };
+        return self.rotations.values().all(|rotation| {
+            // going round in the old order, the new angles can only drop back
+            // once, where they wrap round
+            let drops = (0..rotation.len())
+                .filter(|i| {
+                    let next = rotation[(i + 1) % rotation.len()];
+                    return angle(rotation[*i]) >= angle(next);
+                })
+                .count();
+            return rotation.len() < 2 || drops <= 1;
+        });
+    }
+
+    /// changes the directions an edge leaves its start and end in, after it has
+    /// been redrawn without changing the order edges leave each node in; see
+    /// [`PlanarMap::keeps_rotations`]
+    pub fn set_angles(&mut self, edge: usize, start_angle: f32, end_angle: f32) {
+        let (h, t) = (2 * edge, 2 * edge + 1);
+        self.half_edges[h].angle = start_angle;
+        self.half_edges[t].angle = end_angle;
+        for node in [self.half_edges[h].origin, self.half_edges[t].origin] {
+            let half_edges = &self.half_edges;
+            let rotation = self.rotations.get_mut(&node).unwrap();
+            // the cyclic order is the same, so only where it starts can change
+            rotation.sort_by(|a, b| {
+                half_edges[*a]
+                    .angle
+                    .partial_cmp(&half_edges[*b].angle)
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+    }
+
+    /// the half-edge leaving node just before the given angle, going round in order of
+    /// increasing angle. A new edge at that angle would be in the face to its left.
+    /// None if the node has no edges
+    fn corner(&self, node: usize, angle: f32) -> Option<usize> {
+        let rotation = &self.rotations[&node];
+        let before = rotation
+            .iter()
+            .rev()
+            .find(|h| self.half_edges[**h].angle < angle)
+            .or(rotation.last());
+        return before.copied();
+    }
+
+    fn isolated_face(&self, node: usize) -> usize {
+        return self
+            .faces
+            .iter()
+            .position(|face| face.isolated.contains(&node))
+            .unwrap();
+    }
+
+    /// which of the face's boundaries the half-edge is part of
+    fn boundary_index(&self, face: usize, half_edge: usize) -> usize {
+        return self.faces[face]
+            .boundaries
+            .iter()
+            .position(|b| self.walk(*b).contains(&half_edge))
+            .unwrap();
+    }
+
+    fn insert_into_rotation(&mut self, node: usize, half_edge: usize) {
+        let angle = self.half_edges[half_edge].angle;
+        let half_edges = &self.half_edges;
+        let rotation = self.rotations.get_mut(&node).unwrap();
+        let i = rotation
+            .iter()
+            .position(|h| {
+                half_edges[*h]
+                    .angle
+                    .partial_cmp(&angle)
+                    .unwrap_or(Ordering::Equal)
+                    == Ordering::Greater
+            })
+            .unwrap_or(rotation.len());
+        rotation.insert(i, half_edge);
+    }
+
+    /// links up the half-edges passing through node. A walk arriving at the node
+    /// along the twin of one outgoing half-edge leaves along the one before it
+    fn relink(&mut self, node: usize) {
+        let rotation = &self.rotations[&node];
+        for i in 0..rotation.len() {
+            let before = rotation[i];
+            let after = rotation[(i + 1) % rotation.len()];
+            self.half_edges[twin(after)].next = before;
+            self.half_edges[before].prev = twin(after);
+        }
+    }
+
+    /// the half-edges around the boundary containing this one
+    fn walk(&self, start: usize) -> Vec<usize> {
+        let mut walk = vec![start];
+        let mut current = self.half_edges[start].next;
+        while current != start {
+            walk.push(current);
+            current = self.half_edges[current].next;
+        }
+        return walk;
+    }
+
+    fn as_edges(walk: &[usize]) -> Vec<(usize, bool)> {
+        return walk.iter().map(|h| (h / 2, h.is_multiple_of(2))).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PlanarMap, OUTER_FACE};
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_joining_isolated_nodes() {
+        let mut map = PlanarMap::new();
+        for n in 0..3 {
+            map.add_node(n, OUTER_FACE);
+        }
+        map.add_edge(0, 0.0, 1, PI, |_, _| panic!("no face to split"));
+        map.add_edge(1, 0.0, 2, PI, |_, _| panic!("no face to split"));
+        assert_eq!(map.face_count(), 1);
+        assert_eq!(map.nodes_on_face(OUTER_FACE), vec![0, 1, 2]);
+        // the path is walked there and back, starting from the last edge added
+        assert_eq!(
+            map.face_boundaries(OUTER_FACE),
+            vec![vec![(1, true), (1, false), (0, false), (0, true)]]
+        );
+    }
+
+    #[test]
+    fn test_loop_splits_face() {
+        // a square 0-1-2-3 with node 4 inside it and node 5 outside.
+        // y points down the screen, so angles increase clockwise
+        let mut map = PlanarMap::new();
+        for n in 0..6 {
+            map.add_node(n, OUTER_FACE);
+        }
+        let inside = |_: &[(usize, bool)], node: usize| node == 4;
+        map.add_edge(0, 0.0, 1, PI, inside);
+        map.add_edge(1, PI / 2.0, 2, -PI / 2.0, inside);
+        map.add_edge(2, PI, 3, 0.0, inside);
+        assert_eq!(map.face_count(), 1);
+        map.add_edge(3, -PI / 2.0, 0, PI / 2.0, inside);
+        assert_eq!(map.face_count(), 2);
+
+        // whichever face is the inside of the square has node 4 in it
+        let square_inside = map.face_at(0, PI / 4.0);
+        let square_outside = map.face_at(0, -PI / 4.0);
+        assert_ne!(square_inside, square_outside);
+        assert_eq!(map.nodes_on_face(square_inside), vec![0, 1, 2, 3, 4]);
+        assert_eq!(map.nodes_on_face(square_outside), vec![0, 1, 2, 3, 5]);
+        assert_eq!(map.faces_of(0), {
+            let mut faces = vec![square_inside, square_outside];
+            faces.sort();
+            faces
+        });
+        assert_eq!(map.faces_of(4), vec![square_inside]);
+    }
+
+    #[test]
+    fn test_remove_edge() {
+        // the square from test_loop_splits_face, opened up again along its right side
+        let mut map = PlanarMap::new();
+        for n in 0..6 {
+            map.add_node(n, OUTER_FACE);
+        }
+        let inside = |_: &[(usize, bool)], node: usize| node == 4;
+        map.add_edge(0, 0.0, 1, PI, inside);
+        map.add_edge(1, PI / 2.0, 2, -PI / 2.0, inside);
+        map.add_edge(2, PI, 3, 0.0, inside);
+        map.add_edge(3, -PI / 2.0, 0, PI / 2.0, inside);
+        map.remove_edge(1, inside);
+
+        assert_eq!(map.edge_count(), 3);
+        assert_eq!(map.edge_ends(1), (2, 3));
+        assert_eq!(map.face_count(), 1);
+        assert_eq!(map.nodes_on_face(OUTER_FACE), vec![0, 1, 2, 3, 4, 5]);
+
+        map.remove_node(5);
+        assert_eq!(map.nodes_on_face(OUTER_FACE), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_set_angles() {
+        // the same square as above, redrawn with its corners pulled out
+        let mut map = PlanarMap::new();
+        for n in 0..5 {
+            map.add_node(n, OUTER_FACE);
+        }
+        let inside = |_: &[(usize, bool)], node: usize| node == 4;
+        map.add_edge(0, 0.0, 1, PI, inside);
+        map.add_edge(1, PI / 2.0, 2, -PI / 2.0, inside);
+        map.add_edge(2, PI, 3, 0.0, inside);
+        map.add_edge(3, -PI / 2.0, 0, PI / 2.0, inside);
+        let square_inside = map.face_at(0, PI / 4.0);
+
+        // turn everything at node 0 round by 3 * PI / 4, so edge 3 now leaves it at
+        // 5 * PI / 4, which wraps round to -3 * PI / 4, and the inside faces left
+        map.set_angles(0, 3.0 * PI / 4.0, PI);
+        map.set_angles(3, -PI / 2.0, -3.0 * PI / 4.0);
+        assert_eq!(map.face_at(0, PI), square_inside);
+        assert_eq!(map.face_at(0, -7.0 * PI / 8.0), square_inside);
+        assert_ne!(map.face_at(0, 0.0), square_inside);
+    }
+
+    #[test]
+    fn test_keeps_rotations() {
+        // node 0 with edges leaving it to the right, downwards and to the left
+        let mut map = PlanarMap::new();
+        for n in 0..4 {
+            map.add_node(n, OUTER_FACE);
+        }
+        map.add_edge(0, 0.0, 1, PI, |_, _| false);
+        map.add_edge(0, PI / 2.0, 2, -PI / 2.0, |_, _| false);
+        map.add_edge(0, PI, 3, 0.0, |_, _| false);
+        // turning them all round together keeps their order
+        let turned = [(PI / 2.0, PI), (PI, -PI / 2.0), (-PI / 2.0, 0.0)];
+        assert!(map.keeps_rotations(&turned));
+        // but swapping the first two doesn't
+        let swapped = [(PI / 2.0, PI), (0.0, -PI / 2.0), (PI, 0.0)];
+        assert!(!map.keeps_rotations(&swapped));
+    }
+
+    #[test]
+    fn test_self_loop() {
+        // node 0 loops round itself through node 1
+        let mut map = PlanarMap::new();
+        map.add_node(0, OUTER_FACE);
+        map.add_node(1, OUTER_FACE);
+        map.add_edge(0, -PI / 4.0, 1, -3.0 * PI / 4.0, |_, _| false);
+        map.add_edge(1, 3.0 * PI / 4.0, 0, PI / 4.0, |_, _| false);
+        assert_eq!(map.face_count(), 2);
+        // between the two ends of the loop is its inside, the rest is outside
+        let loop_inside = map.face_at(0, 0.0);
+        let loop_outside = map.face_at(0, PI);
+        assert_ne!(loop_inside, loop_outside);
+        assert_eq!(map.face_at(1, PI), loop_inside);
+        assert_eq!(map.face_at(1, 0.0), loop_outside);
+        assert_eq!(map.faces_of(1).len(), 2);
+    }
+}
@@ -2,46 +2,202 @@ extern crate sdl2;
 
 use sdl2::event::Event;
 // use sdl2::keyboard::Keycode;
+use std::path::PathBuf;
+use std::process::exit;
 use std::time::Duration;
 
-use logic::game::{Game, Player};
-use view::ui::{UI};
-
-pub mod logic;
-pub mod view;
+use sprouts_rs::view::renderer::SdlRenderer;
+use sprouts_rs::view::saved_game::SavedGame;
+use sprouts_rs::view::svg::SvgRenderer;
+use sprouts_rs::view::ui::{load_game, BOARD_SIZE, UI};
 
 const FPS: u32 = 60;
+const USAGE: &str =
+    "usage: sprouts-rs [SAVED_GAME | --layout FILE] [--export-svg FILE] [--export-png FILE]
+       sprouts-rs export [--format dot|json] [--regions] SAVED_GAME";
 
-fn main() {
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
-    let window = video_subsystem.window("sprouts-rs", 800, 600)
+/// how `export` writes out the graph of a saved game
+enum GraphFormat {
+    Dot,
+    Json,
+}
+
+/// what to do, from the command line arguments
+struct Options {
+    saved_game: Option<PathBuf>,
+    // a saved game to take the starting spots from, to move about before playing
+    layout: Option<PathBuf>,
+    // write the saved game to an SVG or PNG file instead of opening a window
+    export_svg: Option<PathBuf>,
+    export_png: Option<PathBuf>,
+    // write the saved game's graph to stdout instead, with the regions each spot touches if asked
+    export_graph: Option<GraphFormat>,
+    regions: bool,
+}
+
+impl Options {
+    fn exporting(&self) -> bool {
+        return self.export_svg.is_some()
+            || self.export_png.is_some()
+            || self.export_graph.is_some();
+    }
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        saved_game: None,
+        layout: None,
+        export_svg: None,
+        export_png: None,
+        export_graph: None,
+        regions: false,
+    };
+    let mut args = std::env::args().skip(1).peekable();
+    let export = args.next_if_eq("export").is_some();
+    if export {
+        options.export_graph = Some(GraphFormat::Dot);
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" if export => {
+                let format = args.next().ok_or("--format needs dot or json")?;
+                options.export_graph = Some(parse_format(&format)?);
+            }
+            "--regions" if export => options.regions = true,
+            "--export-svg" => {
+                let path = args.next().ok_or("--export-svg needs a file name")?;
+                options.export_svg = Some(PathBuf::from(path));
+            }
+            "--layout" => {
+                let path = args.next().ok_or("--layout needs a file name")?;
+                options.layout = Some(PathBuf::from(path));
+            }
+            "--export-png" => {
+                let path = args.next().ok_or("--export-png needs a file name")?;
+                options.export_png = Some(PathBuf::from(path));
+            }
+            _ if options.saved_game.is_none() && !arg.starts_with('-') => {
+                options.saved_game = Some(PathBuf::from(arg));
+            }
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    if export
+        && (options.layout.is_some()
+            || options.export_svg.is_some()
+            || options.export_png.is_some())
+    {
+        return Err(String::from("export only takes --format and --regions"));
+    }
+    if options.saved_game.is_some() && options.layout.is_some() {
+        return Err(String::from(
+            "a saved game can't be given a different layout",
+        ));
+    }
+    if options.exporting() && options.saved_game.is_none() {
+        return Err(String::from("exporting needs a saved game"));
+    }
+    return Ok(options);
+}
+
+fn parse_format(format: &str) -> Result<GraphFormat, String> {
+    return match format {
+        "dot" => Ok(GraphFormat::Dot),
+        "json" => Ok(GraphFormat::Json),
+        _ => Err(format!("unknown format {}, expected dot or json", format)),
+    };
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let saved_game = match (&options.saved_game, &options.layout) {
+        (Some(path), _) => Some(SavedGame::read(path)?),
+        // only the spots are used from a layout, not its edges or moves
+        (_, Some(path)) => Some(SavedGame {
+            spots: SavedGame::read(path)?.spots,
+            ..SavedGame::default()
+        }),
+        (None, None) => None,
+    };
+
+    if let Some(format) = &options.export_graph {
+        let (game, _) = load_game(saved_game.as_ref(), |game| {
+            UI::new(
+                Box::new(SvgRenderer::new(BOARD_SIZE.0, BOARD_SIZE.1)),
+                game.get_nodes(),
+            )
+        })?;
+        let export = game.export(options.regions);
+        match format {
+            GraphFormat::Dot => print!("{}", export.to_dot()),
+            #[cfg(feature = "serde")]
+            GraphFormat::Json => println!("{}", export.to_json()),
+            #[cfg(not(feature = "serde"))]
+            GraphFormat::Json => return Err(String::from("JSON export needs the serde feature")),
+        }
+    }
+    if let Some(path) = &options.export_svg {
+        let (_, ui) = load_game(saved_game.as_ref(), |game| {
+            UI::new(
+                Box::new(SvgRenderer::new(BOARD_SIZE.0, BOARD_SIZE.1)),
+                game.get_nodes(),
+            )
+        })?;
+        ui.export_svg(path)?;
+    }
+    if let Some(path) = &options.export_png {
+        let renderer = SdlRenderer::headless(BOARD_SIZE.0, BOARD_SIZE.1)?;
+        let (_, mut ui) = load_game(saved_game.as_ref(), |game| {
+            UI::new(Box::new(renderer), game.get_nodes())
+        })?;
+        ui.save_png(path)?;
+    }
+    if options.exporting() {
+        return Ok(());
+    }
+
+    let sdl_context = sdl2::init()?;
+    let video_subsystem = sdl_context.video()?;
+    let window = video_subsystem
+        .window("sprouts-rs", BOARD_SIZE.0, BOARD_SIZE.1)
         .position_centered()
+        .resizable()
+        .allow_highdpi()
         .build()
-        .unwrap();
+        .map_err(|e| e.to_string())?;
 
-    let mut event_pump = sdl_context.event_pump().unwrap();
-    
-    let player1 = Player::new(0, String::from("player 1"));
-    let player2 = Player::new(1, String::from("player 2"));
+    let mut event_pump = sdl_context.event_pump()?;
 
-    let mut game = Game::new(player1, player2, 3);
-
-    let mut canvas = UI::new(window, game.get_nodes());
+    let (mut game, mut canvas) = load_game(saved_game.as_ref(), |game| {
+        UI::new(Box::new(SdlRenderer::new(window)), game.get_nodes())
+    })?;
+    // new games start by placing the spots
+    if options.saved_game.is_none() {
+        canvas.start_setup();
+    }
 
     'running: loop {
         for event in event_pump.poll_iter() {
             match event {
-                Event::Quit {..} => {
+                Event::Quit { .. } => {
                     break 'running;
-                },
+                }
                 _ => canvas.process(event, &mut game),
             };
-        } 
+        }
 
         canvas.render();
 
         ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / FPS));
     }
+    return Ok(());
 }
 
+fn main() {
+    let result = parse_args()
+        .map_err(|e| format!("{}\n{}", e, USAGE))
+        .and_then(run);
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
@@ -1,2 +1,16 @@
+pub mod camera;
+pub mod curve;
+pub mod geometry;
+#[cfg(test)]
+mod golden_tests;
+pub mod layout;
+#[cfg(feature = "serde")]
+pub mod point_serde;
+pub mod renderer;
+pub mod saved_game;
+pub mod screenshot;
+pub mod spatial;
+pub mod svg;
+pub mod text;
 pub mod ui;
 pub mod utils;
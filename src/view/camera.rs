@@ -0,0 +1,119 @@
+use sdl2::rect::Point;
+
+/// maps between world coordinates, which everything on the board is stored in,
+/// and screen coordinates in output pixels. A world point p is drawn at
+/// p * scale + offset
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub scale: f32,
+    pub offset: (f32, f32),
+}
+
+impl Camera {
+    /// draws the world as it is
+    pub fn identity() -> Camera {
+        Camera {
+            scale: 1.0,
+            offset: (0.0, 0.0),
+        }
+    }
+
+    /// shows the whole of a world area of the given size as large as possible,
+    /// centred on a screen of the given size
+    pub fn fit(world: (u32, u32), screen: (u32, u32)) -> Camera {
+        let scale = f32::min(
+            screen.0 as f32 / world.0 as f32,
+            screen.1 as f32 / world.1 as f32,
+        );
+        return Camera {
+            scale,
+            offset: (
+                (screen.0 as f32 - world.0 as f32 * scale) / 2.0,
+                (screen.1 as f32 - world.1 as f32 * scale) / 2.0,
+            ),
+        };
+    }
+
+    pub fn to_screen(&self, p: Point) -> Point {
+        return Point::new(
+            (p.x as f32 * self.scale + self.offset.0).round() as i32,
+            (p.y as f32 * self.scale + self.offset.1).round() as i32,
+        );
+    }
+
+    pub fn to_world(&self, p: Point) -> Point {
+        return Point::new(
+            ((p.x as f32 - self.offset.0) / self.scale).round() as i32,
+            ((p.y as f32 - self.offset.1) / self.scale).round() as i32,
+        );
+    }
+
+    /// applies inner first and then this camera
+    pub fn compose(&self, inner: &Camera) -> Camera {
+        return Camera {
+            scale: self.scale * inner.scale,
+            offset: (
+                inner.offset.0 * self.scale + self.offset.0,
+                inner.offset.1 * self.scale + self.offset.1,
+            ),
+        };
+    }
+
+    /// scales everything up by factor, keeping whatever is at centre where it is
+    pub fn zoom_at(&mut self, centre: Point, factor: f32) {
+        let (x, y) = (centre.x as f32, centre.y as f32);
+        self.offset = (
+            x - (x - self.offset.0) * factor,
+            y - (y - self.offset.1) * factor,
+        );
+        self.scale *= factor;
+    }
+
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.offset = (self.offset.0 + dx, self.offset.1 + dy);
+    }
+
+    /// a world distance as a distance on screen
+    pub fn scale_length(&self, length: f32) -> f32 {
+        return length * self.scale;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Camera;
+    use sdl2::rect::Point;
+
+    #[test]
+    fn test_fit() {
+        // a wide screen at twice the size, so there are bars at the sides
+        let camera = Camera::fit((800, 600), (2000, 1200));
+        assert_eq!(camera.scale, 2.0);
+        assert_eq!(camera.offset, (200.0, 0.0));
+        assert_eq!(camera.to_screen(Point::new(0, 0)), Point::new(200, 0));
+        assert_eq!(
+            camera.to_screen(Point::new(800, 600)),
+            Point::new(1800, 1200)
+        );
+        assert_eq!(camera.to_world(Point::new(1000, 600)), Point::new(400, 300));
+
+        assert_eq!(Camera::fit((800, 600), (800, 600)), Camera::identity());
+    }
+
+    #[test]
+    fn test_zoom_and_pan() {
+        let fit = Camera::fit((800, 600), (1600, 1200));
+        let mut view = Camera::identity();
+        // zooming keeps the point under the mouse still
+        view.zoom_at(Point::new(400, 200), 2.0);
+        let camera = view.compose(&fit);
+        assert_eq!(camera.scale, 4.0);
+        assert_eq!(camera.to_screen(Point::new(200, 100)), Point::new(400, 200));
+        assert_eq!(camera.to_world(Point::new(400, 200)), Point::new(200, 100));
+
+        view.pan(40.0, -8.0);
+        let camera = view.compose(&fit);
+        assert_eq!(camera.to_screen(Point::new(200, 100)), Point::new(440, 192));
+        assert_eq!(camera.to_world(Point::new(440, 192)), Point::new(200, 100));
+    }
+}
@@ -0,0 +1,245 @@
+use sdl2::rect::Point;
+
+use crate::view::utils::segment_point_distance;
+
+// how far a point can be from the simplified line before it has to be kept
+const SIMPLIFY_TOLERANCE: f32 = 2.0;
+// approximate length of each segment of a fitted spline
+const SPLINE_SEGMENT_LENGTH: f32 = 8.0;
+// 0.5 gives a centripetal catmull-rom spline, which can't form cusps or loops within a segment
+const SPLINE_ALPHA: f32 = 0.5;
+
+/// turns a stroke recorded from the mouse into a smooth curve through the same
+/// end points, by simplifying it and then fitting a spline through what is left
+pub fn smooth(points: &[Point]) -> Vec<Point> {
+    return catmull_rom(&simplify(points, SIMPLIFY_TOLERANCE));
+}
+
+/// removes points that don't change the shape of the line by more than
+/// `tolerance`, using the Ramer–Douglas–Peucker algorithm.
+/// The first and last points are always kept
+pub fn simplify(points: &[Point], tolerance: f32) -> Vec<Point> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let mut furthest = (first, 0.0);
+        for i in first + 1..last {
+            let distance = segment_point_distance(&points[first], &points[last], &points[i]);
+            if distance > furthest.1 {
+                furthest = (i, distance);
+            }
+        }
+        if furthest.1 > tolerance {
+            keep[furthest.0] = true;
+            ranges.push((first, furthest.0));
+            ranges.push((furthest.0, last));
+        }
+    }
+    return points
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(p, _)| *p)
+        .collect();
+}
+
+/// samples a centripetal catmull-rom spline that passes through every point
+pub fn catmull_rom(points: &[Point]) -> Vec<Point> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let control = points
+        .iter()
+        .map(|p| (p.x as f32, p.y as f32))
+        .collect::<Vec<_>>();
+    // extend the ends so the first and last segments have a neighbour on both sides
+    let before = reflect(control[1], control[0]);
+    let after = reflect(control[control.len() - 2], control[control.len() - 1]);
+
+    let mut curve = vec![points[0]];
+    for i in 0..control.len() - 1 {
+        let p0 = if i == 0 { before } else { control[i - 1] };
+        let p1 = control[i];
+        let p2 = control[i + 1];
+        let p3 = if i + 2 < control.len() {
+            control[i + 2]
+        } else {
+            after
+        };
+        let samples = (distance(p1, p2) / SPLINE_SEGMENT_LENGTH).ceil().max(1.0) as usize;
+        for s in 1..=samples {
+            let p = if s == samples {
+                // land exactly on the control point
+                p2
+            } else {
+                spline_point(p0, p1, p2, p3, s as f32 / samples as f32)
+            };
+            let p = Point::new(p.0.round() as i32, p.1.round() as i32);
+            if *curve.last().unwrap() != p {
+                curve.push(p);
+            }
+        }
+    }
+    return curve;
+}
+
+/// splits a line in two at the point half way along its length.
+/// That point ends the first half and starts the second
+pub fn split_at_midpoint(points: &[Point]) -> (Vec<Point>, Vec<Point>) {
+    let as_f32 = |p: &Point| (p.x as f32, p.y as f32);
+    let length: f32 = points
+        .windows(2)
+        .map(|w| distance(as_f32(&w[0]), as_f32(&w[1])))
+        .sum();
+    let mut remaining = length / 2.0;
+    for i in 1..points.len() {
+        let (a, b) = (as_f32(&points[i - 1]), as_f32(&points[i]));
+        let segment_length = distance(a, b);
+        if remaining > segment_length {
+            remaining -= segment_length;
+            continue;
+        }
+        let t = if segment_length == 0.0 {
+            0.0
+        } else {
+            remaining / segment_length
+        };
+        let mid = Point::new(
+            (a.0 + (b.0 - a.0) * t).round() as i32,
+            (a.1 + (b.1 - a.1) * t).round() as i32,
+        );
+        let mut first = points[..i].to_vec();
+        if *first.last().unwrap() != mid {
+            first.push(mid);
+        }
+        let mut second = vec![mid];
+        second.extend(points[i..].iter().skip_while(|p| **p == mid));
+        return (first, second);
+    }
+    // a single point
+    return (points.to_vec(), points.to_vec());
+}
+
+/// evaluates the segment between p1 and p2 of the spline through p0..p3,
+/// where t goes from 0 at p1 to 1 at p2
+fn spline_point(
+    p0: (f32, f32),
+    p1: (f32, f32),
+    p2: (f32, f32),
+    p3: (f32, f32),
+    t: f32,
+) -> (f32, f32) {
+    // knot values, spaced by distance between the points
+    let t0 = 0.0;
+    let t1 = t0 + distance(p0, p1).powf(SPLINE_ALPHA);
+    let t2 = t1 + distance(p1, p2).powf(SPLINE_ALPHA);
+    let t3 = t2 + distance(p2, p3).powf(SPLINE_ALPHA);
+    let t = t1 + (t2 - t1) * t;
+
+    let lerp = |a: (f32, f32), b: (f32, f32), ta: f32, tb: f32| {
+        if tb - ta == 0.0 {
+            return a;
+        }
+        let u = (t - ta) / (tb - ta);
+        return (a.0 + (b.0 - a.0) * u, a.1 + (b.1 - a.1) * u);
+    };
+    // barry and goldman's pyramidal formulation
+    let a1 = lerp(p0, p1, t0, t1);
+    let a2 = lerp(p1, p2, t1, t2);
+    let a3 = lerp(p2, p3, t2, t3);
+    let b1 = lerp(a1, a2, t0, t2);
+    let b2 = lerp(a2, a3, t1, t3);
+    return lerp(b1, b2, t1, t2);
+}
+
+/// reflects p through the point centre
+fn reflect(p: (f32, f32), centre: (f32, f32)) -> (f32, f32) {
+    return (2.0 * centre.0 - p.0, 2.0 * centre.1 - p.1);
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    return ((a.0 - b.0) * (a.0 - b.0) + (a.1 - b.1) * (a.1 - b.1)).sqrt();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{catmull_rom, simplify, split_at_midpoint};
+    use sdl2::rect::Point;
+
+    #[test]
+    fn test_simplify() {
+        // wobbly straight line
+        let line = (0..20)
+            .map(|x| Point::new(x * 10, x % 2))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            simplify(&line, 2.0),
+            vec![Point::new(0, 0), Point::new(190, 1)]
+        );
+        // corners are kept
+        let corner = vec![
+            Point::new(0, 0),
+            Point::new(50, 1),
+            Point::new(100, 0),
+            Point::new(100, 50),
+            Point::new(101, 100),
+        ];
+        assert_eq!(
+            simplify(&corner, 2.0),
+            vec![Point::new(0, 0), Point::new(100, 0), Point::new(101, 100)]
+        );
+    }
+
+    #[test]
+    fn test_catmull_rom() {
+        let control = vec![Point::new(0, 0), Point::new(100, 0), Point::new(100, 100)];
+        let curve = catmull_rom(&control);
+        // passes through every control point, starting and ending at the same place
+        for p in control.iter() {
+            assert!(curve.contains(p));
+        }
+        assert_eq!(curve.first(), control.first());
+        assert_eq!(curve.last(), control.last());
+        // and is made of short segments
+        for i in 1..curve.len() {
+            let (dx, dy) = (curve[i].x - curve[i - 1].x, curve[i].y - curve[i - 1].y);
+            assert!(dx * dx + dy * dy <= 100);
+        }
+    }
+
+    #[test]
+    fn test_split_at_midpoint() {
+        // most of the samples are bunched up at the start, so the middle sample
+        // is nowhere near the middle of the line
+        let line = vec![
+            Point::new(0, 0),
+            Point::new(2, 0),
+            Point::new(4, 0),
+            Point::new(6, 0),
+            Point::new(100, 0),
+        ];
+        let (first, second) = split_at_midpoint(&line);
+        assert_eq!(
+            first,
+            vec![
+                Point::new(0, 0),
+                Point::new(2, 0),
+                Point::new(4, 0),
+                Point::new(6, 0),
+                Point::new(50, 0)
+            ]
+        );
+        assert_eq!(second, vec![Point::new(50, 0), Point::new(100, 0)]);
+
+        // midpoint lands on an existing point
+        let line = vec![Point::new(0, 0), Point::new(0, 10), Point::new(0, 20)];
+        let (first, second) = split_at_midpoint(&line);
+        assert_eq!(first, vec![Point::new(0, 0), Point::new(0, 10)]);
+        assert_eq!(second, vec![Point::new(0, 10), Point::new(0, 20)]);
+    }
+}
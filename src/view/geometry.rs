@@ -0,0 +1,107 @@
+use crate::view::utils::orientation;
+use sdl2::rect::Point;
+
+/// the direction from a to b, as an angle in radians
+pub fn direction(a: &Point, b: &Point) -> f32 {
+    return ((b.y - a.y) as f32).atan2((b.x - a.x) as f32);
+}
+
+/// twice the signed area of the polygon, which is closed automatically.
+/// Positive when the points go round in order of increasing angle
+pub fn signed_area(polygon: &[Point]) -> i64 {
+    let mut area = 0;
+    for i in 0..polygon.len() {
+        let a = &polygon[i];
+        let b = &polygon[(i + 1) % polygon.len()];
+        area += a.x as i64 * b.y as i64 - b.x as i64 * a.y as i64;
+    }
+    return area;
+}
+
+/// counts how many times the polygon winds around p. The polygon is closed
+/// automatically, so the last point doesn't need to repeat the first.
+/// https://web.archive.org/web/20130126163405/http://geomalgorithms.com/a03-_inclusion.html
+pub fn winding_number(polygon: &[Point], p: &Point) -> i32 {
+    let mut wn = 0;
+    for i in 0..polygon.len() {
+        let a = &polygon[i];
+        let b = &polygon[(i + 1) % polygon.len()];
+        if a.y <= p.y {
+            if b.y > p.y && orientation(a, b, p) > 0 {
+                // upward crossing
+                wn += 1;
+            }
+        } else if b.y <= p.y && orientation(a, b, p) < 0 {
+            // downward crossing
+            wn -= 1;
+        }
+    }
+    return wn;
+}
+
+/// determines whether p lies within the polygon, using the winding number method
+pub fn point_in_polygon(polygon: &[Point], p: &Point) -> bool {
+    return winding_number(polygon, p) != 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{point_in_polygon, signed_area, winding_number};
+    use sdl2::rect::Point;
+
+    fn polygon(points: &[(i32, i32)]) -> Vec<Point> {
+        return points.iter().map(|(x, y)| Point::new(*x, *y)).collect();
+    }
+
+    #[test]
+    fn test_closes_polygon() {
+        // only the implicit closing edge separates (5, 1) from the outside
+        let triangle = polygon(&[(0, 10), (10, 10), (5, 0)]);
+        assert!(point_in_polygon(&triangle, &Point::new(5, 5)));
+        assert!(!point_in_polygon(&triangle, &Point::new(5, 11)));
+        assert!(!point_in_polygon(&triangle, &Point::new(1, 3)));
+    }
+
+    #[test]
+    fn test_concave() {
+        // a U shape, like a curved edge bending back on itself
+        let u = polygon(&[
+            (0, 0),
+            (10, 0),
+            (10, 30),
+            (20, 30),
+            (20, 0),
+            (30, 0),
+            (30, 40),
+            (0, 40),
+        ]);
+        assert!(point_in_polygon(&u, &Point::new(5, 10)));
+        assert!(point_in_polygon(&u, &Point::new(25, 10)));
+        assert!(point_in_polygon(&u, &Point::new(15, 35)));
+        // inside the gap of the U
+        assert!(!point_in_polygon(&u, &Point::new(15, 10)));
+    }
+
+    #[test]
+    fn test_direction() {
+        let clockwise = polygon(&[(0, 0), (10, 0), (10, 10), (0, 10)]);
+        let anticlockwise = clockwise.iter().rev().cloned().collect::<Vec<_>>();
+        let p = Point::new(5, 5);
+        assert_eq!(
+            winding_number(&clockwise, &p),
+            -winding_number(&anticlockwise, &p)
+        );
+        assert_eq!(signed_area(&clockwise), 200);
+        assert_eq!(signed_area(&anticlockwise), -200);
+        assert!(point_in_polygon(&clockwise, &p));
+        assert!(point_in_polygon(&anticlockwise, &p));
+    }
+
+    #[test]
+    fn test_doubled_back_spur() {
+        // a spur that goes out and comes back along the same line doesn't change anything
+        let square = polygon(&[(0, 0), (10, 0), (20, 5), (10, 0), (10, 10), (0, 10)]);
+        assert!(point_in_polygon(&square, &Point::new(5, 5)));
+        assert!(!point_in_polygon(&square, &Point::new(15, 5)));
+    }
+}
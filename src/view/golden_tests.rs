@@ -0,0 +1,107 @@
+//! snapshot tests that draw known positions with SDL's software renderer and
+//! compare them with reference images in tests/golden. Each position is a saved
+//! game, `<name>.txt`, with its reference image next to it as `<name>.png`.
+//! A missing reference fails the test. Setting UPDATE_GOLDEN=1 writes them all
+//! from the current rendering instead; check the new images before committing them
+
+use crate::view::renderer::SdlRenderer;
+use crate::view::saved_game::SavedGame;
+use crate::view::ui::{load_game, BOARD_SIZE, UI};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+const GOLDEN_DIR: &str = "tests/golden";
+// how far apart a channel of two pixels can be before they count as different,
+// to allow for small differences between SDL and font versions
+const CHANNEL_TOLERANCE: u8 = 32;
+// the fraction of pixels that can differ before the images don't match
+const PIXEL_TOLERANCE: f64 = 0.002;
+
+struct Image {
+    width: u32,
+    height: u32,
+    // 8-bit RGBA
+    pixels: Vec<u8>,
+}
+
+fn read_png(path: &Path) -> Image {
+    let decoder = png::Decoder::new(File::open(path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    pixels.truncate(info.buffer_size());
+    return Image {
+        width: info.width,
+        height: info.height,
+        pixels,
+    };
+}
+
+/// draws the saved game in the golden directory to a PNG, returning its path
+fn render(name: &str) -> PathBuf {
+    let saved = SavedGame::read(&Path::new(GOLDEN_DIR).join(format!("{}.txt", name))).unwrap();
+    let renderer = SdlRenderer::headless(BOARD_SIZE.0, BOARD_SIZE.1).unwrap();
+    let (_, mut ui) = load_game(Some(&saved), |game| {
+        return UI::new(Box::new(renderer), game.get_nodes());
+    })
+    .unwrap();
+    let path = std::env::temp_dir().join(format!("sprouts-rs-golden-{}.png", name));
+    ui.save_png(&path).unwrap();
+    return path;
+}
+
+/// the fraction of pixels that differ by more than CHANNEL_TOLERANCE in any channel
+fn difference(a: &Image, b: &Image) -> f64 {
+    let differing = a
+        .pixels
+        .chunks(4)
+        .zip(b.pixels.chunks(4))
+        .filter(|(p, q)| {
+            p.iter()
+                .zip(q.iter())
+                .any(|(x, y)| x.abs_diff(*y) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    return differing as f64 / (a.width * a.height) as f64;
+}
+
+fn check_golden(name: &str) {
+    let rendered = render(name);
+    let reference = Path::new(GOLDEN_DIR).join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::copy(&rendered, &reference).unwrap();
+        eprintln!("wrote reference image {}", reference.display());
+        return;
+    }
+    assert!(
+        reference.exists(),
+        "{} has no reference image; run with UPDATE_GOLDEN=1 to write it from {}",
+        name,
+        rendered.display()
+    );
+    let (actual, expected) = (read_png(&rendered), read_png(&reference));
+    assert_eq!(
+        (actual.width, actual.height),
+        (expected.width, expected.height),
+        "{} is a different size to its reference image",
+        name
+    );
+    let difference = difference(&actual, &expected);
+    assert!(
+        difference <= PIXEL_TOLERANCE,
+        "{:.2}% of pixels differ from the reference image; see {}",
+        difference * 100.0,
+        rendered.display()
+    );
+}
+
+#[test]
+fn golden_three_spots() {
+    check_golden("three_spots");
+}
+
+#[test]
+fn golden_two_moves() {
+    check_golden("two_moves");
+}
@@ -0,0 +1,241 @@
+//! force-directed layout that keeps the drawing planar, following PrEd
+//! (Bertault, "A force-directed algorithm that preserves edge crossing properties").
+//! Every point is moved by less than a third of the smallest gap between a point
+//! and a segment it isn't part of, so no edge can be pushed across another
+
+// the length edges are pulled towards
+const EDGE_LENGTH: f32 = 60.0;
+// how close a spot and another point can get before they push each other apart
+const POINT_REPULSION_RANGE: f32 = 2.0 * EDGE_LENGTH;
+// how close a point can get to a segment before being pushed away from it
+const EDGE_REPULSION_RANGE: f32 = 40.0;
+// how strongly everything is pulled towards the centre, so it doesn't all end up at the sides
+const GRAVITY: f32 = 0.02;
+// how strongly bends in edges are straightened out
+const STIFFNESS: f32 = 1.0;
+// the furthest any point can move in one step
+const MAX_STEP: f32 = 8.0;
+
+type Vector = (f32, f32);
+
+/// points joined up by paths, where each path is one edge of the drawing.
+/// The first `spots` points are the spots, and the rest are bends in the edges
+pub struct Drawing {
+    pub spots: usize,
+    pub points: Vec<Vector>,
+    pub paths: Vec<Vec<usize>>,
+}
+
+impl Drawing {
+    /// adds points along any part of a path longer than EDGE_LENGTH, so that
+    /// the edge has room to bend as it is moved
+    pub fn subdivide(&mut self) {
+        for p in 0..self.paths.len() {
+            let mut path = vec![self.paths[p][0]];
+            for pair in self.paths[p].windows(2) {
+                let (a, b) = (self.points[pair[0]], self.points[pair[1]]);
+                let pieces = (length(sub(b, a)) / EDGE_LENGTH).ceil().max(1.0) as usize;
+                for i in 1..pieces {
+                    let t = i as f32 / pieces as f32;
+                    path.push(self.points.len());
+                    self.points.push(add(a, scale(sub(b, a), t)));
+                }
+                path.push(pair[1]);
+            }
+            self.paths[p] = path;
+        }
+    }
+
+    /// spreads the drawing out over the given number of steps, keeping every
+    /// point within margin of the edges of an area of the given size
+    pub fn relax(&mut self, iterations: usize, size: Vector, margin: f32) {
+        let segments = self.segments();
+        for _ in 0..iterations {
+            let gap = self.smallest_gap(&segments);
+            let max_step = f32::min(MAX_STEP, gap / 3.0 * 0.99);
+            let forces = self.forces(&segments, size);
+            for (p, force) in self.points.iter_mut().zip(forces) {
+                let magnitude = length(force);
+                let step = if magnitude > max_step {
+                    scale(force, max_step / magnitude)
+                } else {
+                    force
+                };
+                // clamping can only shorten a step, so it can't break the limit
+                *p = (
+                    (p.0 + step.0).clamp(margin, size.0 - margin),
+                    (p.1 + step.1).clamp(margin, size.1 - margin),
+                );
+            }
+        }
+    }
+
+    fn segments(&self) -> Vec<(usize, usize)> {
+        return self
+            .paths
+            .iter()
+            .flat_map(|path| path.windows(2).map(|pair| (pair[0], pair[1])))
+            .collect();
+    }
+
+    /// the shortest distance between a point and a segment it isn't an end of
+    fn smallest_gap(&self, segments: &[(usize, usize)]) -> f32 {
+        let mut gap = f32::INFINITY;
+        for (v, p) in self.points.iter().enumerate() {
+            for (a, b) in segments.iter() {
+                if v != *a && v != *b {
+                    let (_, distance) = closest_point(self.points[*a], self.points[*b], *p);
+                    gap = gap.min(distance);
+                }
+            }
+        }
+        return gap;
+    }
+
+    fn forces(&self, segments: &[(usize, usize)], size: Vector) -> Vec<Vector> {
+        let mut forces = vec![(0.0, 0.0); self.points.len()];
+        let centre = (size.0 / 2.0, size.1 / 2.0);
+        for (v, p) in self.points.iter().enumerate() {
+            // spots push apart everything near them. Bends only push away from
+            // segments, or edges would buckle
+            for (u, q) in self.points.iter().enumerate() {
+                let d = length(sub(*p, *q));
+                let spot = u < self.spots || v < self.spots;
+                if spot && u != v && d > 0.0 && d < POINT_REPULSION_RANGE {
+                    let f = scale(sub(*p, *q), EDGE_LENGTH * EDGE_LENGTH / (d * d));
+                    forces[v] = add(forces[v], f);
+                }
+            }
+            // points are pushed away from nearby segments
+            for (a, b) in segments.iter() {
+                if v == *a || v == *b {
+                    continue;
+                }
+                let (closest, d) = closest_point(self.points[*a], self.points[*b], *p);
+                if d > 0.0 && d < EDGE_REPULSION_RANGE {
+                    let f = scale(sub(*p, closest), (EDGE_REPULSION_RANGE - d).powi(2) / d);
+                    forces[v] = add(forces[v], f);
+                }
+            }
+            forces[v] = add(forces[v], scale(sub(centre, *p), GRAVITY));
+        }
+        // the ends of each segment pull together
+        for (a, b) in segments.iter() {
+            let (p, q) = (self.points[*a], self.points[*b]);
+            let f = scale(sub(q, p), length(sub(q, p)) / EDGE_LENGTH);
+            forces[*a] = add(forces[*a], f);
+            forces[*b] = sub(forces[*b], f);
+        }
+        // and each bend in a path is pulled towards the middle of its neighbours
+        for path in self.paths.iter() {
+            for bend in path.windows(3) {
+                let (p, q, r) = (
+                    self.points[bend[0]],
+                    self.points[bend[1]],
+                    self.points[bend[2]],
+                );
+                let middle = scale(add(p, r), 0.5);
+                forces[bend[1]] = add(forces[bend[1]], scale(sub(middle, q), STIFFNESS));
+            }
+        }
+        return forces;
+    }
+}
+
+/// the closest point to p on the segment ab, and how far away it is
+fn closest_point(a: Vector, b: Vector, p: Vector) -> (Vector, f32) {
+    let ab = sub(b, a);
+    let length_sq = ab.0 * ab.0 + ab.1 * ab.1;
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * ab.0 + (p.1 - a.1) * ab.1) / length_sq).clamp(0.0, 1.0)
+    };
+    let closest = add(a, scale(ab, t));
+    return (closest, length(sub(p, closest)));
+}
+
+fn add(a: Vector, b: Vector) -> Vector {
+    return (a.0 + b.0, a.1 + b.1);
+}
+
+fn sub(a: Vector, b: Vector) -> Vector {
+    return (a.0 - b.0, a.1 - b.1);
+}
+
+fn scale(a: Vector, s: f32) -> Vector {
+    return (a.0 * s, a.1 * s);
+}
+
+fn length(a: Vector) -> f32 {
+    return (a.0 * a.0 + a.1 * a.1).sqrt();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closest_point, Drawing};
+
+    fn crosses(a: (f32, f32), b: (f32, f32), c: (f32, f32), d: (f32, f32)) -> bool {
+        let side = |p: (f32, f32), q: (f32, f32), r: (f32, f32)| {
+            ((q.0 - p.0) * (r.1 - p.1) - (r.0 - p.0) * (q.1 - p.1)).signum()
+        };
+        return side(a, b, c) != side(a, b, d) && side(c, d, a) != side(c, d, b);
+    }
+
+    #[test]
+    fn test_relax_keeps_edges_apart() {
+        // two spots joined by a pair of edges bunched up close together, around a third spot
+        let mut drawing = Drawing {
+            spots: 3,
+            points: vec![
+                (300.0, 300.0),
+                (340.0, 300.0),
+                (320.0, 303.0),
+                (320.0, 290.0),
+                (320.0, 310.0),
+            ],
+            paths: vec![vec![0, 3, 1], vec![0, 4, 1]],
+        };
+        drawing.subdivide();
+        drawing.relax(200, (800.0, 600.0), 40.0);
+
+        let segments = drawing.segments();
+        for (i, (a, b)) in segments.iter().enumerate() {
+            for (c, d) in segments[i + 1..].iter() {
+                if a == c || a == d || b == c || b == d {
+                    continue;
+                }
+                let p = &drawing.points;
+                assert!(!crosses(p[*a], p[*b], p[*c], p[*d]));
+            }
+        }
+        // the spot in the middle is still between the two edges
+        let p = &drawing.points;
+        let above = drawing.paths[0]
+            .iter()
+            .map(|v| p[*v].1)
+            .fold(f32::MAX, f32::min);
+        let below = drawing.paths[1]
+            .iter()
+            .map(|v| p[*v].1)
+            .fold(f32::MIN, f32::max);
+        assert!(above < p[2].1 && p[2].1 < below);
+        // and the drawing has spread out
+        assert!(below - above > 40.0);
+        for (x, y) in p.iter() {
+            assert!((40.0..=760.0).contains(x) && (40.0..=560.0).contains(y));
+        }
+    }
+
+    #[test]
+    fn test_closest_point() {
+        assert_eq!(
+            closest_point((0.0, 0.0), (10.0, 0.0), (4.0, 3.0)),
+            ((4.0, 0.0), 3.0)
+        );
+        assert_eq!(
+            closest_point((0.0, 0.0), (10.0, 0.0), (14.0, 3.0)),
+            ((10.0, 0.0), 5.0)
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! serde support for the sdl2 points that board geometry is stored as, which
+//! don't implement it themselves. A point is written as an `[x, y]` pair. Use
+//! with `#[serde(with = "...")]` on a field of the matching type
+
+use sdl2::rect::Point;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// a single point, such as where a spot is
+pub mod point {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(point: &Point, serializer: S) -> Result<S::Ok, S::Error> {
+        return (point.x, point.y).serialize(serializer);
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Point, D::Error> {
+        let (x, y) = <(i32, i32)>::deserialize(deserializer)?;
+        return Ok(Point::new(x, y));
+    }
+}
+
+/// a list of points, such as the path of an edge
+pub mod points {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(points: &[Point], serializer: S) -> Result<S::Ok, S::Error> {
+        let pairs = points.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+        return pairs.serialize(serializer);
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Point>, D::Error> {
+        let pairs = Vec::<(i32, i32)>::deserialize(deserializer)?;
+        return Ok(pairs.into_iter().map(|(x, y)| Point::new(x, y)).collect());
+    }
+}
+
+/// a list of spot ids and where each spot is
+pub mod spots {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        spots: &[(usize, Point)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let spots = spots
+            .iter()
+            .map(|(n, p)| (*n, (p.x, p.y)))
+            .collect::<Vec<_>>();
+        return spots.serialize(serializer);
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(usize, Point)>, D::Error> {
+        let spots = Vec::<(usize, (i32, i32))>::deserialize(deserializer)?;
+        return Ok(spots
+            .into_iter()
+            .map(|(n, (x, y))| (n, Point::new(x, y)))
+            .collect());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::view::screenshot;
+use crate::view::text::TextCache;
+use log::warn;
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{Canvas, RenderTarget, TextureCreator, TextureQuery};
+use sdl2::surface::Surface;
+use sdl2::video::{FullscreenType, Window};
+use std::path::Path;
+
+const FONT_PATH: &str = "./assets/UbuntuNerdFont-Medium.ttf";
+
+/// the drawing operations the UI needs, so it can draw to something other than an SDL window
+pub trait Renderer {
+    /// the size of the area being drawn on, in pixels
+    fn size(&self) -> (u32, u32);
+    /// pixels per unit of the coordinates mouse events use. More than 1 on
+    /// high-DPI displays
+    fn pixel_ratio(&self) -> f32 {
+        return 1.0;
+    }
+    /// fills the whole area with one colour
+    fn clear(&mut self, color: Color);
+    fn filled_circle(&mut self, centre: Point, radius: i32, color: Color);
+    fn thick_line(&mut self, a: Point, b: Point, width: u8, color: Color);
+    /// draws a line through each of the points in turn
+    fn polyline(&mut self, points: &[Point], width: u8, color: Color) {
+        for segment in points.windows(2) {
+            self.thick_line(segment[0], segment[1], width, color);
+        }
+    }
+    /// draws text centred on position, with the font at the given size in pixels
+    fn text(&mut self, text: &str, position: Point, size: u16, color: Color);
+    /// shows everything drawn since the last clear
+    fn present(&mut self);
+    fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        return Err(String::from("This renderer can't go fullscreen"));
+    }
+    /// saves what has been drawn since the last clear as a PNG image
+    fn save_png(&self, _path: &Path) -> Result<(), String> {
+        return Err(String::from("This renderer can't save PNG images"));
+    }
+}
+
+/// something SdlRenderer can draw to
+pub trait SdlTarget: RenderTarget + Sized {
+    /// the size of the target in the units mouse events use
+    fn point_size(canvas: &Canvas<Self>) -> (u32, u32);
+    fn toggle_fullscreen(canvas: &mut Canvas<Self>) -> Result<(), String>;
+}
+
+impl SdlTarget for Window {
+    fn point_size(canvas: &Canvas<Window>) -> (u32, u32) {
+        return canvas.window().size();
+    }
+
+    fn toggle_fullscreen(canvas: &mut Canvas<Window>) -> Result<(), String> {
+        let window = canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        return window.set_fullscreen(fullscreen);
+    }
+}
+
+impl SdlTarget for Surface<'_> {
+    fn point_size(canvas: &Canvas<Self>) -> (u32, u32) {
+        return canvas.surface().size();
+    }
+
+    fn toggle_fullscreen(_canvas: &mut Canvas<Self>) -> Result<(), String> {
+        return Err(String::from("Can't go fullscreen without a window"));
+    }
+}
+
+/// draws with SDL, either to a window or to a surface in memory
+pub struct SdlRenderer<T: SdlTarget> {
+    canvas: Canvas<T>,
+    text: TextCache<T::Context>,
+}
+
+impl SdlRenderer<Window> {
+    pub fn new(window: Window) -> SdlRenderer<Window> {
+        let canvas = window.into_canvas().build().unwrap();
+        let texture_creator = canvas.texture_creator();
+        return SdlRenderer::with_canvas(canvas, texture_creator);
+    }
+}
+
+impl SdlRenderer<Surface<'static>> {
+    /// draws in memory with SDL's software renderer, without needing a window
+    pub fn headless(width: u32, height: u32) -> Result<SdlRenderer<Surface<'static>>, String> {
+        let surface = Surface::new(width, height, PixelFormatEnum::RGBA32)?;
+        let canvas = surface.into_canvas()?;
+        let texture_creator = canvas.texture_creator();
+        return Ok(SdlRenderer::with_canvas(canvas, texture_creator));
+    }
+}
+
+impl<T: SdlTarget> SdlRenderer<T> {
+    fn with_canvas(
+        mut canvas: Canvas<T>,
+        texture_creator: TextureCreator<T::Context>,
+    ) -> SdlRenderer<T> {
+        canvas.set_draw_color(Color::RGB(255, 255, 255));
+        canvas.clear();
+        canvas.present();
+
+        SdlRenderer {
+            canvas,
+            text: TextCache::new(texture_creator, FONT_PATH),
+        }
+    }
+}
+
+impl<T: SdlTarget> Renderer for SdlRenderer<T> {
+    fn size(&self) -> (u32, u32) {
+        return self.canvas.output_size().unwrap_or((0, 0));
+    }
+
+    fn pixel_ratio(&self) -> f32 {
+        let (points, _) = T::point_size(&self.canvas);
+        if points == 0 {
+            return 1.0;
+        }
+        return self.size().0 as f32 / points as f32;
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.canvas.set_draw_color(color);
+        self.canvas.clear();
+    }
+
+    fn filled_circle(&mut self, centre: Point, radius: i32, color: Color) {
+        let _ = self
+            .canvas
+            .filled_circle(centre.x as i16, centre.y as i16, radius as i16, color);
+    }
+
+    fn thick_line(&mut self, a: Point, b: Point, width: u8, color: Color) {
+        let _ = self
+            .canvas
+            .thick_line(a.x as i16, a.y as i16, b.x as i16, b.y as i16, width, color);
+    }
+
+    fn text(&mut self, text: &str, position: Point, size: u16, color: Color) {
+        let texture = match self.text.get(text, size, color) {
+            Ok(texture) => texture,
+            Err(e) => {
+                warn!("{}", e);
+                return;
+            }
+        };
+        let TextureQuery { width, height, .. } = texture.query();
+        let target = Rect::from_center(position, width, height);
+        if let Err(e) = self.canvas.copy(texture, None, Some(target)) {
+            warn!("{}", e);
+        }
+    }
+
+    fn present(&mut self) {
+        self.canvas.present();
+    }
+
+    fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        return T::toggle_fullscreen(&mut self.canvas);
+    }
+
+    fn save_png(&self, path: &Path) -> Result<(), String> {
+        let (width, height) = self.canvas.output_size()?;
+        let pixels = self.canvas.read_pixels(None, PixelFormatEnum::RGBA32)?;
+        return screenshot::write_png(path, width, height, &pixels);
+    }
+}
@@ -0,0 +1,215 @@
+use sdl2::rect::Point;
+use std::fs;
+use std::path::Path;
+
+/// an edge as the player drew it, before it was smoothed
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SavedMove {
+    pub start: usize,
+    pub end: usize,
+    #[cfg_attr(feature = "serde", serde(with = "crate::view::point_serde::points"))]
+    pub path: Vec<Point>,
+}
+
+/// an edge that is part of the starting position, exactly as it is drawn.
+/// Unlike a move, no spot is added to it
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SavedEdge {
+    pub start: usize,
+    pub end: usize,
+    #[cfg_attr(feature = "serde", serde(with = "crate::view::point_serde::points"))]
+    pub points: Vec<Point>,
+}
+
+/// something done to the board after it was set up
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SavedAction {
+    Move(SavedMove),
+    /// the board was tidied up, moving spots and reshaping edges
+    Tidy,
+}
+
+/// the starting position and everything done since, enough to replay a game.
+/// Saved as text, one spot, edge or action per line:
+///
+/// ```text
+/// spot <id> <x> <y>
+/// edge <start> <end> <x>,<y> <x>,<y> ...
+/// move <start> <end> <x>,<y> <x>,<y> ...
+/// tidy
+/// ```
+///
+/// Blank lines and lines starting with `#` are ignored
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SavedGame {
+    #[cfg_attr(feature = "serde", serde(with = "crate::view::point_serde::spots"))]
+    pub spots: Vec<(usize, Point)>,
+    pub edges: Vec<SavedEdge>,
+    pub actions: Vec<SavedAction>,
+}
+
+impl SavedGame {
+    pub fn read(path: &Path) -> Result<SavedGame, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        return SavedGame::parse(&text);
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        return fs::write(path, self.to_text()).map_err(|e| e.to_string());
+    }
+
+    pub fn parse(text: &str) -> Result<SavedGame, String> {
+        let mut game = SavedGame::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| format!("line {}: {}", i + 1, message);
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("spot") => {
+                    let numbers = words
+                        .map(|w| w.parse::<i64>())
+                        .collect::<Result<Vec<_>, _>>();
+                    match numbers.as_deref() {
+                        Ok([id, x, y]) if *id >= 0 => game
+                            .spots
+                            .push((*id as usize, Point::new(*x as i32, *y as i32))),
+                        _ => return Err(error("expected `spot <id> <x> <y>`")),
+                    }
+                }
+                Some(kind @ ("edge" | "move")) => {
+                    let start = words.next().and_then(|w| w.parse().ok());
+                    let end = words.next().and_then(|w| w.parse().ok());
+                    let path = words.map(parse_point).collect::<Option<Vec<_>>>();
+                    let (start, end, path) = match (start, end, path) {
+                        (Some(start), Some(end), Some(path)) if !path.is_empty() => {
+                            (start, end, path)
+                        }
+                        _ => {
+                            let usage = format!("expected `{} <start> <end> <x>,<y> ...`", kind);
+                            return Err(error(&usage));
+                        }
+                    };
+                    if kind == "edge" {
+                        game.edges.push(SavedEdge {
+                            start,
+                            end,
+                            points: path,
+                        });
+                    } else {
+                        game.actions
+                            .push(SavedAction::Move(SavedMove { start, end, path }));
+                    }
+                }
+                Some("tidy") if words.next().is_none() => game.actions.push(SavedAction::Tidy),
+                _ => return Err(error("expected `spot`, `edge`, `move` or `tidy`")),
+            }
+        }
+        return Ok(game);
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# sprouts-rs saved game\n");
+        for (id, pos) in self.spots.iter() {
+            text += &format!("spot {} {} {}\n", id, pos.x, pos.y);
+        }
+        for edge in self.edges.iter() {
+            text += &format!("edge {} {}", edge.start, edge.end);
+            for p in edge.points.iter() {
+                text += &format!(" {},{}", p.x, p.y);
+            }
+            text += "\n";
+        }
+        for action in self.actions.iter() {
+            match action {
+                SavedAction::Move(m) => {
+                    text += &format!("move {} {}", m.start, m.end);
+                    for p in m.path.iter() {
+                        text += &format!(" {},{}", p.x, p.y);
+                    }
+                    text += "\n";
+                }
+                SavedAction::Tidy => text += "tidy\n",
+            }
+        }
+        return text;
+    }
+}
+
+fn parse_point(word: &str) -> Option<Point> {
+    let (x, y) = word.split_once(',')?;
+    return Some(Point::new(x.parse().ok()?, y.parse().ok()?));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SavedAction, SavedEdge, SavedGame, SavedMove};
+    use sdl2::rect::Point;
+
+    fn example() -> SavedGame {
+        return SavedGame {
+            spots: vec![(0, Point::new(400, 450)), (1, Point::new(-3, 20))],
+            edges: vec![SavedEdge {
+                start: 0,
+                end: 0,
+                points: vec![
+                    Point::new(400, 450),
+                    Point::new(500, 500),
+                    Point::new(300, 500),
+                    Point::new(400, 450),
+                ],
+            }],
+            actions: vec![
+                SavedAction::Move(SavedMove {
+                    start: 0,
+                    end: 1,
+                    path: vec![
+                        Point::new(400, 450),
+                        Point::new(200, 100),
+                        Point::new(-3, 20),
+                    ],
+                }),
+                SavedAction::Tidy,
+            ],
+        };
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let game = example();
+        assert_eq!(SavedGame::parse(&game.to_text()), Ok(game));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let game = example();
+        let json = serde_json::to_string(&game).unwrap();
+        assert!(json.contains("[400,450]"));
+        assert_eq!(serde_json::from_str::<SavedGame>(&json).unwrap(), game);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            SavedGame::parse("spot 0 1 2\n\nspot 1 2"),
+            Err(String::from("line 3: expected `spot <id> <x> <y>`"))
+        );
+        assert!(SavedGame::parse("move 0 1").is_err());
+        assert!(SavedGame::parse("move 0 1 3;4").is_err());
+        assert_eq!(
+            SavedGame::parse("edge 0"),
+            Err(String::from(
+                "line 1: expected `edge <start> <end> <x>,<y> ...`"
+            ))
+        );
+        assert!(SavedGame::parse("jump 0").is_err());
+        assert!(SavedGame::parse("tidy 1").is_err());
+    }
+}
@@ -0,0 +1,38 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// writes 8-bit RGBA pixels, row by row from the top left, to a PNG file
+pub fn write_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    return writer.write_image_data(pixels).map_err(|e| e.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_png;
+    use std::fs::File;
+
+    #[test]
+    fn test_write_png() {
+        let path = std::env::temp_dir().join("sprouts-rs-test-write-png.png");
+        // a red and a blue pixel above a green and a transparent one
+        let pixels = [
+            255, 0, 0, 255, 0, 0, 255, 255, //
+            0, 255, 0, 255, 0, 0, 0, 0,
+        ];
+        write_png(&path, 2, 2, &pixels).unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((info.width, info.height), (2, 2));
+        assert_eq!(&buffer[..info.buffer_size()], &pixels);
+    }
+}
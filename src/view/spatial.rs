@@ -0,0 +1,143 @@
+use sdl2::rect::Point;
+use std::cmp::{max, min};
+use std::collections::HashMap;
+
+/// a uniform grid over line segments, used to find the segments near a new
+/// one without checking every segment on the board
+pub struct SegmentGrid {
+    cell_size: i32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    segments: Vec<(Point, Point)>,
+}
+
+impl SegmentGrid {
+    pub fn new(cell_size: i32) -> SegmentGrid {
+        SegmentGrid {
+            cell_size,
+            cells: HashMap::new(),
+            segments: Vec::new(),
+        }
+    }
+
+    /// adds the segment ab to every cell its bounding box overlaps
+    pub fn insert(&mut self, a: Point, b: Point) {
+        let id = self.segments.len();
+        self.segments.push((a, b));
+        let ((x0, y0), (x1, y1)) = cell_range(self.cell_size, &a, &b, 0);
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                self.cells.entry((x, y)).or_default().push(id);
+            }
+        }
+    }
+
+    /// adds every segment of a polyline
+    pub fn insert_polyline(&mut self, points: &[Point]) {
+        for i in 1..points.len() {
+            self.insert(points[i - 1], points[i]);
+        }
+    }
+
+    /// finds the segments which could intersect ab.
+    /// Each segment is returned once, in the order it was inserted
+    pub fn query(&self, a: &Point, b: &Point) -> Vec<&(Point, Point)> {
+        let ((x0, y0), (x1, y1)) = cell_range(self.cell_size, a, b, 0);
+        let mut ids = Vec::new();
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    ids.extend_from_slice(cell);
+                }
+            }
+        }
+        ids.sort_unstable();
+        ids.dedup();
+        return ids.into_iter().map(|id| &self.segments[id]).collect();
+    }
+}
+
+/// a uniform grid over numbered points, used to find the spots near a
+/// segment without checking every spot on the board
+pub struct PointGrid {
+    cell_size: i32,
+    cells: HashMap<(i32, i32), Vec<(usize, Point)>>,
+}
+
+impl PointGrid {
+    pub fn new(cell_size: i32) -> PointGrid {
+        PointGrid {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, id: usize, p: Point) {
+        let ((x, y), _) = cell_range(self.cell_size, &p, &p, 0);
+        self.cells.entry((x, y)).or_default().push((id, p));
+    }
+
+    /// finds the points which could be within distance of the segment ab,
+    /// in order of id
+    pub fn query(&self, a: &Point, b: &Point, distance: i32) -> Vec<(usize, Point)> {
+        let ((x0, y0), (x1, y1)) = cell_range(self.cell_size, a, b, distance);
+        let mut points = Vec::new();
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    points.extend_from_slice(cell);
+                }
+            }
+        }
+        points.sort_unstable_by_key(|(id, _)| *id);
+        return points;
+    }
+}
+
+/// the cells at opposite corners of the bounding box of ab, grown by margin
+fn cell_range(cell_size: i32, a: &Point, b: &Point, margin: i32) -> ((i32, i32), (i32, i32)) {
+    let cell = |v: i32| v.div_euclid(cell_size);
+    return (
+        (cell(min(a.x, b.x) - margin), cell(min(a.y, b.y) - margin)),
+        (cell(max(a.x, b.x) + margin), cell(max(a.y, b.y) + margin)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PointGrid, SegmentGrid};
+    use sdl2::rect::Point;
+
+    #[test]
+    fn test_query() {
+        let mut grid = SegmentGrid::new(10);
+        // spans several cells, so is stored more than once
+        grid.insert(Point::new(0, 0), Point::new(35, 5));
+        grid.insert(Point::new(100, 100), Point::new(105, 95));
+        grid.insert(Point::new(-20, -20), Point::new(-15, -12));
+
+        let near = grid.query(&Point::new(20, 2), &Point::new(28, 8));
+        assert_eq!(near, vec![&(Point::new(0, 0), Point::new(35, 5))]);
+
+        let near = grid.query(&Point::new(-18, -18), &Point::new(-5, -5));
+        assert_eq!(near, vec![&(Point::new(-20, -20), Point::new(-15, -12))]);
+
+        assert!(grid
+            .query(&Point::new(50, 50), &Point::new(60, 60))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_point_query() {
+        let mut grid = PointGrid::new(10);
+        grid.insert(3, Point::new(5, 5));
+        grid.insert(1, Point::new(38, 12));
+        grid.insert(2, Point::new(-50, 70));
+
+        let near = grid.query(&Point::new(20, 5), &Point::new(30, 5), 20);
+        assert_eq!(near, vec![(1, Point::new(38, 12)), (3, Point::new(5, 5))]);
+        // without the margin, neither end of the segment reaches the cells either point is in
+        assert!(grid
+            .query(&Point::new(20, 5), &Point::new(25, 5), 0)
+            .is_empty());
+    }
+}
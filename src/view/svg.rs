@@ -0,0 +1,141 @@
+use crate::view::renderer::Renderer;
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+use std::fs;
+use std::path::Path;
+
+const FONT_FAMILY: &str = "Ubuntu, sans-serif";
+
+/// builds up a standalone SVG document from the drawing operations
+pub struct SvgRenderer {
+    width: u32,
+    height: u32,
+    elements: Vec<String>,
+}
+
+impl SvgRenderer {
+    pub fn new(width: u32, height: u32) -> SvgRenderer {
+        SvgRenderer {
+            width,
+            height,
+            elements: Vec::new(),
+        }
+    }
+
+    /// the document drawn so far
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+            w = self.width,
+            h = self.height
+        );
+        for element in self.elements.iter() {
+            svg += "  ";
+            svg += element;
+            svg += "\n";
+        }
+        svg += "</svg>\n";
+        return svg;
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        return fs::write(path, self.to_svg()).map_err(|e| e.to_string());
+    }
+}
+
+impl Renderer for SvgRenderer {
+    fn size(&self) -> (u32, u32) {
+        return (self.width, self.height);
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.elements.clear();
+        self.elements.push(format!(
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+            hex(color)
+        ));
+    }
+
+    fn filled_circle(&mut self, centre: Point, radius: i32, color: Color) {
+        self.elements.push(format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+            centre.x,
+            centre.y,
+            radius,
+            hex(color)
+        ));
+    }
+
+    fn thick_line(&mut self, a: Point, b: Point, width: u8, color: Color) {
+        self.polyline(&[a, b], width, color);
+    }
+
+    fn polyline(&mut self, points: &[Point], width: u8, color: Color) {
+        let points = points
+            .iter()
+            .map(|p| format!("{},{}", p.x, p.y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.elements.push(format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+            points,
+            hex(color),
+            width
+        ));
+    }
+
+    fn text(&mut self, text: &str, position: Point, size: u16, color: Color) {
+        self.elements.push(format!(
+            "<text x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\">{}</text>",
+            position.x,
+            position.y,
+            FONT_FAMILY,
+            size,
+            hex(color),
+            escape(text)
+        ));
+    }
+
+    fn present(&mut self) {}
+}
+
+fn hex(color: Color) -> String {
+    return format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b);
+}
+
+/// escapes the characters that can't appear as they are in XML text
+fn escape(text: &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SvgRenderer;
+    use crate::view::renderer::Renderer;
+    use sdl2::pixels::Color;
+    use sdl2::rect::Point;
+
+    #[test]
+    fn test_svg_elements() {
+        let mut svg = SvgRenderer::new(80, 60);
+        svg.clear(Color::RGB(255, 255, 255));
+        svg.filled_circle(Point::new(10, 20), 5, Color::RGB(0, 0, 0));
+        svg.polyline(
+            &[Point::new(0, 0), Point::new(5, 5), Point::new(9, 0)],
+            5,
+            Color::RGB(255, 0, 16),
+        );
+        svg.text("a<b", Point::new(1, 2), 25, Color::RGB(0, 255, 0));
+        let text = svg.to_svg();
+        assert!(text.starts_with("<svg "));
+        assert!(text.contains("<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>"));
+        assert!(text.contains("<circle cx=\"10\" cy=\"20\" r=\"5\" fill=\"#000000\"/>"));
+        assert!(text.contains("points=\"0,0 5,5 9,0\""));
+        assert!(text.contains("stroke=\"#ff0010\""));
+        assert!(text.contains(">a&lt;b</text>"));
+        assert!(text.ends_with("</svg>\n"));
+    }
+}
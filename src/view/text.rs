@@ -0,0 +1,71 @@
+use once_cell::sync::Lazy;
+use sdl2::pixels::Color;
+use sdl2::render::{Texture, TextureCreator};
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use std::collections::HashMap;
+
+// textures kept before the cache is emptied, so messages that change a lot
+// (like toasts) can't fill up video memory
+const MAX_CACHED_TEXTS: usize = 256;
+// fonts kept loaded before they are all dropped. Zooming asks for a new size
+// at every step, but only a few are in use at once
+const MAX_CACHED_FONTS: usize = 8;
+
+static TTF_CONTEXT: Lazy<Sdl2TtfContext> =
+    Lazy::new(|| return sdl2::ttf::init().map_err(|e| e.to_string()).unwrap());
+
+/// textures of text that has already been rendered, keyed by the text, font size
+/// and colour, so each label only has to be rendered once rather than every frame
+pub struct TextCache<C> {
+    texture_creator: TextureCreator<C>,
+    font_path: &'static str,
+    // the font loaded at each size that has been asked for
+    fonts: HashMap<u16, Font<'static, 'static>>,
+    textures: HashMap<(String, u16, Color), Texture>,
+}
+
+impl<C> TextCache<C> {
+    pub fn new(texture_creator: TextureCreator<C>, font_path: &'static str) -> TextCache<C> {
+        TextCache {
+            texture_creator,
+            font_path,
+            fonts: HashMap::new(),
+            textures: HashMap::new(),
+        }
+    }
+
+    /// the texture for text drawn at the given size and colour, rendering it if
+    /// it isn't cached
+    pub fn get(&mut self, text: &str, size: u16, color: Color) -> Result<&Texture, String> {
+        let key = (text.to_string(), size, color);
+        if !self.textures.contains_key(&key) {
+            if self.textures.len() >= MAX_CACHED_TEXTS {
+                self.clear();
+            }
+            if !self.fonts.contains_key(&size) {
+                if self.fonts.len() >= MAX_CACHED_FONTS {
+                    self.fonts.clear();
+                }
+                let font = TTF_CONTEXT.load_font(self.font_path, size)?;
+                self.fonts.insert(size, font);
+            }
+            let surface = self.fonts[&size]
+                .render(text)
+                .blended(color)
+                .map_err(|e| e.to_string())?;
+            let texture = self
+                .texture_creator
+                .create_texture_from_surface(&surface)
+                .map_err(|e| e.to_string())?;
+            self.textures.insert(key.clone(), texture);
+        }
+        return Ok(&self.textures[&key]);
+    }
+
+    fn clear(&mut self) {
+        for (_, texture) in self.textures.drain() {
+            // safe as the renderer that created the textures outlives the cache
+            unsafe { texture.destroy() };
+        }
+    }
+}
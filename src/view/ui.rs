@@ -1,28 +1,64 @@
-use crate::logic::game::{Game, GameState};
-use crate::view::utils::{edges_intersect, TextRendering};
-use log::warn;
-use once_cell::sync::Lazy;
+use crate::logic::game::{EdgeDirections, Game, GameState, MoveError, Player};
+use crate::view::camera::Camera;
+use crate::view::layout::Drawing;
+use crate::view::renderer::Renderer;
+use crate::view::saved_game::{SavedAction, SavedEdge, SavedGame, SavedMove};
+use crate::view::spatial::{PointGrid, SegmentGrid};
+use crate::view::svg::SvgRenderer;
+use crate::view::utils::{segment_intersection, segment_point_distance, Intersection};
+use crate::view::{curve, geometry};
 use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
 use sdl2::pixels::Color;
-use sdl2::rect::{Point, Rect};
-use sdl2::render::Canvas;
-use sdl2::ttf::Sdl2TtfContext;
-use sdl2::video::Window;
-use sdl2::{gfx::primitives::DrawRenderer, mouse::MouseButton};
+use sdl2::rect::Point;
 use std::cmp::min;
 use std::collections::HashMap;
 use std::f32::consts::PI;
+use std::path::Path;
+use std::time::{Duration, Instant};
 use std::vec::Vec;
 
+/// size of the board, in the world coordinates everything on it is stored in
+pub const BOARD_SIZE: (u32, u32) = (800, 600);
+// how many spots a new game starts with, before any are added during setup
+const NEW_GAME_SPOTS: usize = 3;
 const NODE_RADIUS: i32 = 15;
+const EDGE_WIDTH: f32 = 5.0;
+const LABEL_SIZE: u16 = 25;
+// how far in the player can zoom, and how much each click of the mouse wheel zooms by
+const MAX_ZOOM: f32 = 8.0;
+const ZOOM_STEP: f32 = 1.25;
 // min squared distance between segments on an edge
 const MIN_EDGE_SEGMENT_DISTANCE: i32 = 100;
+// min distance between a spot and any edge that doesn't end at it
+const SPOT_CLEARANCE: f32 = NODE_RADIUS as f32 + 3.0;
+// size of the cells in the grid used to look up edge segments
+const SEGMENT_GRID_CELL_SIZE: i32 = 40;
+// how far apart the centres of spots have to be when they are placed
+const SPOT_SPACING: i32 = 3 * NODE_RADIUS;
+// how close to an edge a click has to be to pick it
+const EDGE_PICK_DISTANCE: f32 = 8.0;
+// how long a toast message stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+// how long the board is left to settle when it is tidied, and how far it is kept from the sides
+const TIDY_ITERATIONS: usize = 300;
+const TIDY_MARGIN: f32 = 40.0;
+// how closely the shapes of edges are followed when they are tidied
+const TIDY_SIMPLIFY_TOLERANCE: f32 = 4.0;
+// where the save, export and screenshot keys write to
+const SAVE_PATH: &str = "sprouts-save.txt";
+const SVG_PATH: &str = "sprouts.svg";
+const PNG_PATH: &str = "sprouts.png";
 
-static TTF_CONTEXT: Lazy<Sdl2TtfContext> =
-    Lazy::new(|| return sdl2::ttf::init().map_err(|e| e.to_string()).unwrap());
-
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct UINode {
+    #[cfg_attr(feature = "serde", serde(skip))]
     focused: bool,
+    // whether the edge being drawn could legally end here. None when not drawing
+    #[cfg_attr(feature = "serde", serde(skip))]
+    legal: Option<bool>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::view::point_serde::point"))]
     pos: Point,
 }
 
@@ -30,6 +66,7 @@ impl UINode {
     pub fn new() -> UINode {
         UINode {
             focused: false,
+            legal: None,
             pos: Point::new(0, 0),
         }
     }
@@ -37,69 +74,186 @@ impl UINode {
     pub fn at_position(pos: Point) -> UINode {
         UINode {
             focused: false,
+            legal: None,
             pos,
         }
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct UIEdge {
+    #[cfg_attr(feature = "serde", serde(with = "crate::view::point_serde::points"))]
+    points: Vec<Point>,
+}
+
 pub struct UI {
-    canvas: Canvas<Window>,
+    renderer: Box<dyn Renderer>,
+    // maps the board onto the renderer's output, whatever its size
+    camera: Camera,
+    // the player's zoom and pan, applied on top of fitting the board to the output
+    view: Camera,
+    panning: bool,
+    // before play starts, spots can be added and dragged around
+    setting_up: bool,
+    dragging: Option<usize>,
+    // spots and edges can be added and removed freely, ignoring turns
+    sandbox: bool,
     nodes: HashMap<usize, UINode>,
-    edges: Vec<Vec<Point>>,
+    // indexed by the game's edge ids
+    edges: Vec<UIEdge>,
+    // index over the segments of every edge, for collision checks
+    segment_grid: SegmentGrid,
     drawing: bool,
     drawing_start: usize,
     drawing_edge: Vec<Point>,
+    // indexes over the segments of the edge being drawn and over the spots, so
+    // each new point is checked against what's near it. Spots can't move while
+    // an edge is being drawn, so they're indexed when it starts
+    stroke_grid: SegmentGrid,
+    spot_grid: PointGrid,
+    // whether every point of the edge being drawn is still close to its start spot
+    leaving_start: bool,
     mouse_pos: Point,
-    font: sdl2::ttf::Font<'static, 'static>,
+    // the mouse position in output pixels, for zooming around
+    mouse_pixel: Point,
+    toast: Option<(String, Instant)>,
+    // the starting spots and moves played so far, for saving the game
+    history: SavedGame,
 }
 
 impl UI {
-    pub fn new(window: Window, nodes: Vec<usize>) -> UI {
-        let mut canvas = window.into_canvas().build().unwrap();
-        let res = canvas.set_logical_size(800, 600);
-        if !res.is_ok() {
-            warn!("{}", res.unwrap_err());
-        }
-        canvas.set_draw_color(Color::RGB(255, 255, 255));
-        canvas.clear();
-        canvas.present();
-
+    pub fn new(renderer: Box<dyn Renderer>, nodes: Vec<usize>) -> UI {
         let nodes_map = nodes.iter().map(|n| (n.clone(), UINode::new()));
         let mut ui = UI {
-            canvas,
+            renderer,
+            camera: Camera::identity(),
+            view: Camera::identity(),
+            panning: false,
+            setting_up: false,
+            dragging: None,
+            sandbox: false,
             nodes: nodes_map.collect::<HashMap<_, _>>(),
             drawing: false,
             drawing_start: 0,
             drawing_edge: Vec::new(),
+            stroke_grid: SegmentGrid::new(SEGMENT_GRID_CELL_SIZE),
+            spot_grid: PointGrid::new(SEGMENT_GRID_CELL_SIZE),
+            leaving_start: true,
             mouse_pos: Point::new(0, 0),
+            mouse_pixel: Point::new(0, 0),
             edges: Vec::new(),
-            font: TTF_CONTEXT
-                .load_font("./assets/UbuntuNerdFont-Medium.ttf", 25)
-                .unwrap(),
+            segment_grid: SegmentGrid::new(SEGMENT_GRID_CELL_SIZE),
+            toast: None,
+            history: SavedGame::default(),
         };
         ui.auto_layout_nodes();
+        ui.history.spots = ui.spot_positions();
         return ui;
     }
 
+    /// replays a saved game onto the board. The game should be new, with a
+    /// spot for every id up to the largest in the saved game
+    pub fn load(&mut self, saved: &SavedGame, game: &mut Game) -> Result<(), String> {
+        // ids missing from the saved game are spots removed in a sandbox
+        let mut missing = self
+            .nodes
+            .keys()
+            .filter(|id| !saved.spots.iter().any(|(s, _)| s == *id))
+            .copied()
+            .collect::<Vec<_>>();
+        missing.sort_unstable();
+        for id in missing {
+            game.remove_spot(id).map_err(|e| e.to_string())?;
+            self.nodes.remove(&id);
+        }
+        for (id, pos) in saved.spots.iter() {
+            match self.nodes.get_mut(id) {
+                Some(node) => node.pos = *pos,
+                None => return Err(MoveError::InvalidSpot(*id).to_string()),
+            }
+        }
+        for (i, edge) in saved.edges.iter().enumerate() {
+            self.add_edge(edge, game)
+                .map_err(|e| format!("edge {}: {}", i + 1, e))?;
+        }
+        self.history.spots = self.spot_positions();
+        self.history.edges = saved.edges.clone();
+        for (i, action) in saved.actions.iter().enumerate() {
+            let result = match action {
+                SavedAction::Move(m) => self
+                    .play_edge(m.start, m.end, m.path.clone(), game)
+                    .map_err(|e| e.to_string()),
+                SavedAction::Tidy => self.tidy(game),
+            };
+            result.map_err(|e| format!("action {}: {}", i + 1, e))?;
+        }
+        return Ok(());
+    }
+
+    /// lets spots be added and moved until Enter is pressed to start playing.
+    /// The game should be new
+    pub fn start_setup(&mut self) {
+        self.setting_up = true;
+    }
+
+    /// writes the spots, edges and labels on the board to a standalone SVG file
+    pub fn export_svg(&self, path: &Path) -> Result<(), String> {
+        let mut svg = SvgRenderer::new(BOARD_SIZE.0, BOARD_SIZE.1);
+        svg.clear(Color::RGB(255, 255, 255));
+        Self::draw_board(&mut svg, &Camera::identity(), &self.nodes, &self.edges);
+        return svg.write(path);
+    }
+
     pub fn process(&mut self, event: Event, game: &mut Game) {
+        if let Event::KeyDown {
+            keycode: Some(key), ..
+        } = event
+        {
+            self.process_key(key, game);
+            return;
+        }
+        // the window may have been resized since the last frame
+        self.update_camera();
+        if self.process_camera(&event) {
+            return;
+        }
+        if self.setting_up {
+            self.process_setup(event, game);
+            return;
+        }
         if !matches!(game.state, GameState::ACTIVE) {
             return;
         }
 
         match event {
+            Event::MouseButtonDown {
+                x,
+                y,
+                mouse_btn: MouseButton::Right,
+                ..
+            } if self.sandbox && !self.drawing => {
+                let pos = self.to_world(x, y);
+                if let Err(err) = self.remove_at(pos, game) {
+                    self.show_toast(err.to_string());
+                }
+            }
             Event::MouseButtonDown {
                 x, y, mouse_btn, ..
             } => {
                 if mouse_btn == MouseButton::Left {
-                    if let Some(node) = self.find_node_at(x, y) {
+                    let pos = self.to_world(x, y);
+                    if let Some(node) = self.find_node_at(pos.x, pos.y) {
                         let node_pos = self.nodes.get(&node).unwrap().pos;
                         if !self.drawing {
-                            self.drawing = true;
-                            self.drawing_start = node;
-                            self.drawing_edge.push(Point::new(node_pos.x, node_pos.y));
+                            self.start_drawing(node, node_pos);
+                            self.mark_legal_partners(game);
                         }
                     } else {
                         self.drawing = false;
+                        if self.sandbox && self.can_place_spot(&pos, None) {
+                            self.add_spot(pos, game);
+                            self.record_position(game);
+                        }
                     }
                 }
             }
@@ -107,45 +261,38 @@ impl UI {
                 mouse_btn, x, y, ..
             } => {
                 if mouse_btn == MouseButton::Left {
-                    if let Some(node) = self.find_node_at(x, y) {
-                        let node_pos = self.nodes.get(&node).unwrap().pos;
-                        if self.drawing {
-                            if let Some(new_node) = game.do_turn(self.drawing_start, node) {
-                                self.drawing_edge.last_mut().unwrap().x = node_pos.x;
-                                self.drawing_edge.last_mut().unwrap().y = node_pos.y;
-                                self.edges.push(self.drawing_edge.clone());
-                                let new_pos = Self::bisect_pos(&self.drawing_edge);
-                                self.nodes.insert(new_node, UINode::at_position(new_pos));
-                                // end turn
-                                game.end_turn(
-                                    |node, polygon| self.point_in_polygon(&node, &polygon),
-                                    |nodes| self.point_bounds(&nodes),
-                                )
-                            }
+                    if self.drawing {
+                        let pos = self.to_world(x, y);
+                        if let Err(err) = self.finish_edge(pos.x, pos.y, game) {
+                            self.show_toast(err.to_string());
                         }
                     }
                     self.drawing = false;
                     self.drawing_edge.clear();
+                    for node in self.nodes.values_mut() {
+                        node.legal = None;
+                    }
                 }
             }
             Event::MouseMotion { x, y, .. } => {
-                self.mouse_pos.x = x;
-                self.mouse_pos.y = y;
+                self.mouse_pos = self.to_world(x, y);
                 // unhighlight all nodes
                 for node in self.nodes.values_mut() {
                     node.focused = false;
                 }
-                // highlight node if alive
-                if let Some(node) = self.find_node_at(x, y) {
-                    if game.is_node_alive(node) {
-                        self.nodes.get_mut(&node).unwrap().focused = true;
-                    }
+                // highlight node if alive, or if it is a legal end for the edge being drawn
+                if let Some(node) = self.find_node_at(self.mouse_pos.x, self.mouse_pos.y) {
+                    let ui_node = self.nodes.get_mut(&node).unwrap();
+                    ui_node.focused = match ui_node.legal {
+                        Some(legal) => legal,
+                        None => game.is_node_alive(node),
+                    };
                 }
                 // update edge path
-                if self.drawing {
-                    if self.can_add_to_edge(&self.mouse_pos) {
-                        self.drawing_edge.push(self.mouse_pos);
-                        // Self::refine_edge(&mut self.drawing_edge);
+                if self.drawing && self.is_new_edge_point(&self.mouse_pos) {
+                    match self.can_add_to_edge(&self.mouse_pos) {
+                        Ok(()) => self.add_to_edge(self.mouse_pos),
+                        Err(err) => self.show_toast(err.to_string()),
                     }
                 }
             }
@@ -154,54 +301,302 @@ impl UI {
     }
 
     pub fn render(&mut self) {
-        self.canvas.set_draw_color(Color::RGB(255, 255, 255));
-        self.canvas.clear();
+        self.draw();
+        self.renderer.present();
+    }
 
-        for (id, node) in self.nodes.iter() {
-            let color;
-            if node.focused {
-                color = Color::RGB(50, 50, 50);
+    /// draws the board as it currently looks and saves it as a PNG image
+    pub fn save_png(&mut self, path: &Path) -> Result<(), String> {
+        self.draw();
+        return self.renderer.save_png(path);
+    }
+
+    /// draws everything on screen, ready to be presented
+    fn draw(&mut self) {
+        self.update_camera();
+        self.renderer.clear(Color::RGB(255, 255, 255));
+
+        Self::draw_board(
+            self.renderer.as_mut(),
+            &self.camera,
+            &self.nodes,
+            &self.edges,
+        );
+        if self.drawing {
+            Self::draw_edge(self.renderer.as_mut(), &self.camera, &self.drawing_edge);
+        }
+
+        if let Some(hint) = self.hint() {
+            let ratio = self.renderer.pixel_ratio();
+            let (width, _) = self.renderer.size();
+            self.renderer.text(
+                hint,
+                Point::new(width as i32 / 2, (30.0 * ratio) as i32),
+                (LABEL_SIZE as f32 * ratio * 0.8) as u16,
+                Color::RGB(0, 0, 200),
+            );
+        }
+        if let Some((message, shown_at)) = &self.toast {
+            if shown_at.elapsed() < TOAST_DURATION {
+                // the toast isn't on the board, so is only scaled for the display
+                let ratio = self.renderer.pixel_ratio();
+                let (width, height) = self.renderer.size();
+                self.renderer.text(
+                    message,
+                    Point::new(width as i32 / 2, height as i32 - (30.0 * ratio) as i32),
+                    (LABEL_SIZE as f32 * ratio) as u16,
+                    Color::RGB(200, 0, 0),
+                );
             } else {
-                color = Color::RGB(0, 0, 0);
+                self.toast = None;
             }
-            let _ = self.canvas.filled_circle(
-                node.pos.x as i16,
-                node.pos.y as i16,
-                NODE_RADIUS as i16,
+        }
+    }
+
+    /// instructions shown at the top of the window in the modes that need them
+    fn hint(&self) -> Option<&'static str> {
+        if self.setting_up {
+            return Some("Click to add spots and drag them into place, then press Enter to start");
+        }
+        if self.sandbox {
+            return Some(
+                "Sandbox: click to add spots and right click to remove things. B to leave",
+            );
+        }
+        return None;
+    }
+
+    /// draws the spots, their labels and the finished edges
+    fn draw_board(
+        renderer: &mut dyn Renderer,
+        camera: &Camera,
+        nodes: &HashMap<usize, UINode>,
+        edges: &[UIEdge],
+    ) {
+        // in order of id, so the same board is always drawn the same way
+        let mut ids = nodes.keys().collect::<Vec<_>>();
+        ids.sort_unstable();
+        for id in ids {
+            let node = &nodes[id];
+            let color = match (node.legal, node.focused) {
+                (Some(true), true) => Color::RGB(0, 200, 0),
+                (Some(true), false) => Color::RGB(0, 140, 0),
+                (Some(false), _) => Color::RGB(200, 200, 200),
+                (None, true) => Color::RGB(50, 50, 50),
+                (None, false) => Color::RGB(0, 0, 0),
+            };
+            renderer.filled_circle(
+                camera.to_screen(node.pos),
+                camera.scale_length(NODE_RADIUS as f32).round() as i32,
                 color,
             );
-            self.canvas.render_text(
-                &self.font,
-                format!("{}", id),
-                node.pos.offset(NODE_RADIUS, NODE_RADIUS),
+            renderer.text(
+                &format!("{}", id),
+                camera.to_screen(node.pos.offset(NODE_RADIUS, NODE_RADIUS)),
+                camera.scale_length(LABEL_SIZE as f32).round() as u16,
                 Color::RGB(0, 255, 0),
             );
         }
+        for edge in edges {
+            Self::draw_edge(renderer, camera, &edge.points);
+        }
+    }
 
-        let draw_edge = |line: &Vec<Point>| {
-            for i in 0..line.len() - 1 {
-                let start_pos = &line[i];
-                let end_pos = &line[i + 1];
-                let _ = self.canvas.thick_line(
-                    start_pos.x as i16,
-                    start_pos.y as i16,
-                    end_pos.x as i16,
-                    end_pos.y as i16,
-                    5,
-                    Color::RGB(0, 0, 0),
-                );
+    fn draw_edge(renderer: &mut dyn Renderer, camera: &Camera, points: &[Point]) {
+        let points = points
+            .iter()
+            .map(|p| camera.to_screen(*p))
+            .collect::<Vec<_>>();
+        let width = camera.scale_length(EDGE_WIDTH).round().clamp(1.0, 255.0) as u8;
+        renderer.polyline(&points, width, Color::RGB(0, 0, 0));
+    }
+
+    /// fits the board to the renderer's output, leaving bars at the sides or
+    /// top and bottom if their shapes are different, then zooms and pans it
+    fn update_camera(&mut self) {
+        let fit = Camera::fit(BOARD_SIZE, self.renderer.size());
+        self.camera = self.view.compose(&fit);
+    }
+
+    /// zooms with the mouse wheel and pans while the middle button is held.
+    /// Returns whether the event was used
+    fn process_camera(&mut self, event: &Event) -> bool {
+        if let Event::MouseMotion { x, y, .. } = *event {
+            self.mouse_pixel = self.to_pixel(x, y);
+        }
+        match *event {
+            Event::MouseWheel { y, .. } if y != 0 => {
+                let factor = ZOOM_STEP.powi(y);
+                // the view's scale is how far it is zoomed in from fitting the board
+                let zoom = (self.view.scale * factor).clamp(1.0, MAX_ZOOM);
+                self.view.zoom_at(self.mouse_pixel, zoom / self.view.scale);
+                if zoom == 1.0 {
+                    // fully zoomed out, so show the whole board again
+                    self.view = Camera::identity();
+                }
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Middle,
+                ..
+            } => self.panning = true,
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Middle,
+                ..
+            } => self.panning = false,
+            Event::MouseMotion { xrel, yrel, .. } if self.panning => {
+                let ratio = self.renderer.pixel_ratio();
+                self.view.pan(xrel as f32 * ratio, yrel as f32 * ratio);
             }
+            _ => return false,
+        }
+        self.update_camera();
+        return true;
+    }
+
+    /// the position of the mouse at (x, y) in output pixels, as mouse events
+    /// aren't in pixels on high-DPI displays
+    fn to_pixel(&self, x: i32, y: i32) -> Point {
+        let ratio = self.renderer.pixel_ratio();
+        return Point::new(
+            (x as f32 * ratio).round() as i32,
+            (y as f32 * ratio).round() as i32,
+        );
+    }
+
+    /// the point on the board under the mouse at (x, y)
+    fn to_world(&self, x: i32, y: i32) -> Point {
+        return self.camera.to_world(self.to_pixel(x, y));
+    }
+
+    fn process_key(&mut self, key: Keycode, game: &mut Game) {
+        let result = match key {
+            Keycode::S => self.history.write(Path::new(SAVE_PATH)).map(|_| SAVE_PATH),
+            Keycode::E => self.export_svg(Path::new(SVG_PATH)).map(|_| SVG_PATH),
+            Keycode::P => self.save_png(Path::new(PNG_PATH)).map(|_| PNG_PATH),
+            Keycode::Return if self.setting_up => {
+                self.setting_up = false;
+                self.dragging = None;
+                return;
+            }
+            Keycode::B if !self.drawing && !self.setting_up => {
+                self.sandbox = !self.sandbox;
+                game.set_sandbox(self.sandbox);
+                if self.sandbox {
+                    self.record_position(game);
+                }
+                return;
+            }
+            Keycode::T if !self.drawing && !self.setting_up => {
+                if let Err(err) = self.tidy(game) {
+                    self.show_toast(err);
+                }
+                return;
+            }
+            Keycode::Num0 => {
+                self.view = Camera::identity();
+                return;
+            }
+            Keycode::F11 => {
+                if let Err(err) = self.renderer.toggle_fullscreen() {
+                    self.show_toast(err);
+                }
+                return;
+            }
+            _ => return,
         };
+        match result {
+            Ok(path) => self.show_toast(format!("Saved {}", path)),
+            Err(err) => self.show_toast(err),
+        }
+    }
 
-        if self.drawing {
-            draw_edge(&self.drawing_edge);
+    /// adds a spot where the board is clicked, or picks up the spot clicked on
+    /// and moves it with the mouse
+    fn process_setup(&mut self, event: Event, game: &mut Game) {
+        match event {
+            Event::MouseButtonDown {
+                x,
+                y,
+                mouse_btn: MouseButton::Left,
+                ..
+            } => {
+                let pos = self.to_world(x, y);
+                if let Some(node) = self.find_node_at(pos.x, pos.y) {
+                    self.dragging = Some(node);
+                } else if self.can_place_spot(&pos, None) {
+                    self.add_spot(pos, game);
+                } else {
+                    self.show_toast(String::from("Spots need space around them"));
+                }
+            }
+            Event::MouseMotion { x, y, .. } => {
+                self.mouse_pos = self.to_world(x, y);
+                if let Some(id) = self.dragging {
+                    if self.can_place_spot(&self.mouse_pos, Some(id)) {
+                        self.nodes.get_mut(&id).unwrap().pos = self.mouse_pos;
+                    }
+                }
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                ..
+            } => self.dragging = None,
+            _ => return,
         }
+        self.history.spots = self.spot_positions();
+    }
 
-        for edge in &self.edges {
-            draw_edge(&edge);
+    /// whether a spot can be put at pos, on the board and not too close to any
+    /// other spot. The spot being moved, if there is one, is ignored
+    fn can_place_spot(&self, pos: &Point, moving: Option<usize>) -> bool {
+        let (width, height) = (BOARD_SIZE.0 as i32, BOARD_SIZE.1 as i32);
+        if pos.x < NODE_RADIUS
+            || pos.y < NODE_RADIUS
+            || pos.x > width - NODE_RADIUS
+            || pos.y > height - NODE_RADIUS
+        {
+            return false;
+        }
+        let clear_of_edges = self.edges.iter().all(|edge| {
+            edge.points
+                .windows(2)
+                .all(|s| segment_point_distance(&s[0], &s[1], pos) >= SPOT_CLEARANCE)
+        });
+        return clear_of_edges
+            && self.nodes.iter().all(|(id, node)| {
+                Some(*id) == moving
+                    || Self::point_distance(&node.pos, pos) >= SPOT_SPACING * SPOT_SPACING
+            });
+    }
+
+    fn add_spot(&mut self, pos: Point, game: &mut Game) {
+        let id = game.add_spot(|walk| self.walk_contains(walk, &pos, &[]));
+        self.nodes.insert(id, UINode::at_position(pos));
+    }
+
+    /// removes the spot or edge at pos. Spots can only be removed once they
+    /// have no edges
+    fn remove_at(&mut self, pos: Point, game: &mut Game) -> Result<(), MoveError> {
+        if let Some(node) = self.find_node_at(pos.x, pos.y) {
+            game.remove_spot(node)?;
+            self.nodes.remove(&node);
+        } else if let Some(edge) = self.find_edge_at(&pos) {
+            self.edges.remove(edge);
+            self.index_edges();
+            game.remove_edge(edge, |walk, node| self.face_contains(walk, node, &[]))?;
+        } else {
+            return Ok(());
         }
+        self.record_position(game);
+        return Ok(());
+    }
 
-        self.canvas.present();
+    fn find_edge_at(&self, p: &Point) -> Option<usize> {
+        return self.edges.iter().position(|edge| {
+            edge.points
+                .windows(2)
+                .any(|s| segment_point_distance(&s[0], &s[1], p) < EDGE_PICK_DISTANCE)
+        });
     }
 
     fn find_node_at(&self, x: i32, y: i32) -> Option<usize> {
@@ -216,103 +611,451 @@ impl UI {
         return None;
     }
 
+    /// completes the edge being drawn at the node under (x, y) and plays it as a
+    /// turn. In a sandbox the edge joins its ends directly instead, without a
+    /// new spot on it
+    fn finish_edge(&mut self, x: i32, y: i32, game: &mut Game) -> Result<(), MoveError> {
+        let node = self.find_node_at(x, y).ok_or(MoveError::NoEndSpot)?;
+        let node_pos = self.nodes.get(&node).unwrap().pos;
+        // snap the end of the edge to the centre of the spot
+        let mut path = self.drawing_edge.clone();
+        if path.len() > 1 {
+            path.pop();
+        }
+        path.push(node_pos);
+        if self.sandbox {
+            let edge = SavedEdge {
+                start: self.drawing_start,
+                end: node,
+                points: curve::smooth(&path),
+            };
+            self.add_edge(&edge, game)?;
+            self.record_position(game);
+            return Ok(());
+        }
+        return self.play_edge(self.drawing_start, node, path, game);
+    }
+
+    /// smooths a path drawn from start to end, checks it and plays it as a turn
+    fn play_edge(
+        &mut self,
+        start: usize,
+        end: usize,
+        path: Vec<Point>,
+        game: &mut Game,
+    ) -> Result<(), MoveError> {
+        // the smoothed edge is what gets drawn, so it's what has to be valid
+        let edge = curve::smooth(&path);
+        self.check_edge(&edge, start, end)?;
+        // the new node goes half way along the edge, splitting it in two
+        let (first, second) = curve::split_at_midpoint(&edge);
+        // each half needs a second point to say which way it leaves its ends
+        if first.len() < 2 || second.len() < 2 {
+            return Err(MoveError::TooShort);
+        }
+        let directions = EdgeDirections {
+            start: geometry::direction(&first[0], &first[1]),
+            middle: (
+                geometry::direction(&first[first.len() - 1], &first[first.len() - 2]),
+                geometry::direction(&second[0], &second[1]),
+            ),
+            end: geometry::direction(&second[second.len() - 1], &second[second.len() - 2]),
+        };
+        let new_node = game.do_turn(start, end, directions, |walk, node| {
+            self.face_contains(walk, node, &[&first, &second])
+        })?;
+        let new_pos = *second.first().unwrap();
+        self.nodes.insert(new_node, UINode::at_position(new_pos));
+        self.segment_grid.insert_polyline(&first);
+        self.segment_grid.insert_polyline(&second);
+        self.edges.push(UIEdge { points: first });
+        self.edges.push(UIEdge { points: second });
+        self.record(SavedAction::Move(SavedMove { start, end, path }), game);
+        // end turn
+        game.end_turn();
+        return Ok(());
+    }
+
+    /// spreads the spots out and reshapes the edges to suit, without moving
+    /// anything into a different region
+    fn tidy(&mut self, game: &mut Game) -> Result<(), String> {
+        let mut ids = self.nodes.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+        let to_vector = |p: &Point| (p.x as f32, p.y as f32);
+        // the spots come first in the drawing, followed by the bends in the edges
+        let mut drawing = Drawing {
+            spots: ids.len(),
+            points: ids
+                .iter()
+                .map(|id| to_vector(&self.nodes[id].pos))
+                .collect(),
+            paths: Vec::new(),
+        };
+        for edge in self.edges.iter() {
+            let points = curve::simplify(&edge.points, TIDY_SIMPLIFY_TOLERANCE);
+            let spot_at = |p: &Point| {
+                return ids
+                    .iter()
+                    .position(|id| self.nodes[id].pos == *p)
+                    .ok_or(String::from("Edges have to end on spots to be tidied"));
+            };
+            let mut path = vec![spot_at(&points[0])?];
+            for p in points[1..points.len() - 1].iter() {
+                path.push(drawing.points.len());
+                drawing.points.push(to_vector(p));
+            }
+            path.push(spot_at(&points[points.len() - 1])?);
+            drawing.paths.push(path);
+        }
+        drawing.subdivide();
+        let size = (BOARD_SIZE.0 as f32, BOARD_SIZE.1 as f32);
+        drawing.relax(TIDY_ITERATIONS, size, TIDY_MARGIN);
+
+        let to_point = |v: &usize| {
+            let (x, y) = drawing.points[*v];
+            return Point::new(x.round() as i32, y.round() as i32);
+        };
+        let spots = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, to_point(&i)))
+            .collect::<Vec<_>>();
+        let lines = drawing
+            .paths
+            .iter()
+            .map(|path| {
+                let mut line = path.iter().map(to_point).collect::<Vec<_>>();
+                line.dedup();
+                return line;
+            })
+            .collect::<Vec<_>>();
+        let curves = lines.iter().map(|line| curve::catmull_rom(line)).collect();
+
+        // rounding to whole pixels or smoothing the edges could make them touch,
+        // so smooth edges are only used if they don't, and straight ones after that
+        let old_spots = self.spot_positions();
+        let old_edges = self.edges.iter().map(|e| e.points.clone()).collect();
+        self.set_board(&spots, curves);
+        if self.board_clashes() {
+            self.set_board(&spots, lines);
+        }
+        if self.board_clashes() {
+            self.set_board(&old_spots, old_edges);
+            return Err(String::from(
+                "Couldn't tidy the board without edges touching",
+            ));
+        }
+        let directions = self
+            .edges
+            .iter()
+            .map(|edge| {
+                let points = &edge.points;
+                return (
+                    geometry::direction(&points[0], &points[1]),
+                    geometry::direction(&points[points.len() - 1], &points[points.len() - 2]),
+                );
+            })
+            .collect::<Vec<_>>();
+        // the regions are only kept if the edges still go round each spot in
+        // the same order
+        if !game.keeps_edge_order(&directions) {
+            self.set_board(&old_spots, old_edges);
+            return Err(String::from(
+                "Couldn't tidy the board without reordering edges",
+            ));
+        }
+        for (i, (start, end)) in directions.into_iter().enumerate() {
+            game.set_edge_directions(i, start, end);
+        }
+        self.record(SavedAction::Tidy, game);
+        return Ok(());
+    }
+
+    /// adds an edge from a saved starting position, without adding a spot to it
+    fn add_edge(&mut self, edge: &SavedEdge, game: &mut Game) -> Result<(), MoveError> {
+        let points = &edge.points;
+        if points.len() < 2 {
+            return Err(MoveError::TooShort);
+        }
+        for (id, p) in [
+            (edge.start, &points[0]),
+            (edge.end, &points[points.len() - 1]),
+        ] {
+            let node = self.nodes.get(&id).ok_or(MoveError::InvalidSpot(id))?;
+            if node.pos != *p {
+                return Err(MoveError::NoEndSpot);
+            }
+        }
+        self.check_edge(points, edge.start, edge.end)?;
+        game.add_edge(
+            edge.start,
+            geometry::direction(&points[0], &points[1]),
+            edge.end,
+            geometry::direction(&points[points.len() - 1], &points[points.len() - 2]),
+            |walk, node| self.face_contains(walk, node, &[points]),
+        )?;
+        self.segment_grid.insert_polyline(points);
+        self.edges.push(UIEdge {
+            points: points.clone(),
+        });
+        return Ok(());
+    }
+
+    /// adds an action to the history, or outside of a sandbox, or saves the
+    /// whole board as a starting position in one, as that can't be replayed
+    fn record(&mut self, action: SavedAction, game: &Game) {
+        if self.sandbox {
+            self.record_position(game);
+        } else {
+            self.history.actions.push(action);
+        }
+    }
+
+    /// replaces the history with the board as it is now, as a starting position
+    fn record_position(&mut self, game: &Game) {
+        self.history = SavedGame {
+            spots: self.spot_positions(),
+            edges: self
+                .edges
+                .iter()
+                .enumerate()
+                .map(|(i, edge)| {
+                    let (start, end) = game.edge_ends(i);
+                    return SavedEdge {
+                        start,
+                        end,
+                        points: edge.points.clone(),
+                    };
+                })
+                .collect(),
+            actions: Vec::new(),
+        };
+    }
+
+    /// moves the spots and replaces the edges, keeping the order of the edges
+    fn set_board(&mut self, spots: &[(usize, Point)], edges: Vec<Vec<Point>>) {
+        for (id, pos) in spots.iter() {
+            self.nodes.get_mut(id).unwrap().pos = *pos;
+        }
+        self.edges = edges.into_iter().map(|points| UIEdge { points }).collect();
+        self.index_edges();
+    }
+
+    /// rebuilds the segment grid from the edges
+    fn index_edges(&mut self) {
+        self.segment_grid = SegmentGrid::new(SEGMENT_GRID_CELL_SIZE);
+        for edge in self.edges.iter() {
+            self.segment_grid.insert_polyline(&edge.points);
+        }
+    }
+
+    /// whether any edges on the board touch other than at their ends, or come
+    /// closer to a spot than a drawn edge is allowed to
+    fn board_clashes(&self) -> bool {
+        for edge in self.edges.iter() {
+            let points = &edge.points;
+            for i in 1..points.len() {
+                let (a, b) = (&points[i - 1], &points[i]);
+                // the segments either side of ab are joined to it
+                let neighbours = [i.checked_sub(2), Some(i - 1), Some(i)]
+                    .into_iter()
+                    .flatten()
+                    .filter(|j| j + 1 < points.len())
+                    .map(|j| (points[j], points[j + 1]))
+                    .collect::<Vec<_>>();
+                for (c, d) in self.segment_grid.query(a, b) {
+                    if !neighbours.contains(&(*c, *d)) && self.segments_clash(c, d, a, b) {
+                        return true;
+                    }
+                }
+                // as when drawing, an edge can come close to the spots it joins
+                let ends = [points[0], points[points.len() - 1]];
+                for node in self.nodes.values() {
+                    if !ends.contains(&node.pos)
+                        && segment_point_distance(a, b, &node.pos) < SPOT_CLEARANCE
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+        return false;
+    }
+
+    /// shows a message at the bottom of the window for a few seconds
+    fn show_toast(&mut self, message: String) {
+        self.toast = Some((message, Instant::now()));
+    }
+
+    /// flags every node as a legal or illegal end point for the edge
+    /// starting at drawing_start, so they can be highlighted or dimmed
+    fn mark_legal_partners(&mut self, game: &Game) {
+        let partners = game.legal_partners(self.drawing_start);
+        for (id, node) in self.nodes.iter_mut() {
+            node.legal = Some(partners.contains(id));
+        }
+    }
+
+    fn spot_positions(&self) -> Vec<(usize, Point)> {
+        let mut spots = self
+            .nodes
+            .iter()
+            .map(|(id, node)| (*id, node.pos))
+            .collect::<Vec<_>>();
+        spots.sort_unstable_by_key(|(id, _)| *id);
+        return spots;
+    }
+
+    /// spaces the spots out evenly round a circle, in order of id so that the
+    /// same spots are always put in the same places
     fn auto_layout_nodes(&mut self) {
-        let (width, height) = self.canvas.logical_size();
+        let (width, height) = BOARD_SIZE;
         let center = Point::new(width as i32 / 2, height as i32 / 2);
         let margin = 150.0;
         let radius = min(width, height) as f32 / 2.0 - margin;
         let count = self.nodes.len();
-        for (i, node) in self.nodes.values_mut().enumerate() {
+        let mut ids = self.nodes.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+        for (i, id) in ids.iter().enumerate() {
             let t = 2.0 * PI * i as f32 / (count) as f32;
             let x = center.x + (t.sin() * radius) as i32;
             let y = center.y + (t.cos() * radius) as i32;
-            node.pos = Point::new(x, y);
+            self.nodes.get_mut(id).unwrap().pos = Point::new(x, y);
         }
     }
 
-    fn can_add_to_edge(&self, b: &Point) -> bool {
-        if self.drawing_edge.is_empty() {
-            return true;
+    /// whether b is far enough from the end of the edge being drawn to be
+    /// worth adding as a new point. Zooming in allows closer points, so finer
+    /// strokes can be drawn
+    fn is_new_edge_point(&self, b: &Point) -> bool {
+        let zoom = self.view.scale * self.view.scale;
+        return match self.drawing_edge.last() {
+            Some(a) => Self::point_distance(a, b) as f32 >= MIN_EDGE_SEGMENT_DISTANCE as f32 / zoom,
+            None => true,
+        };
+    }
+
+    /// starts drawing an edge from the spot node, at pos
+    fn start_drawing(&mut self, node: usize, pos: Point) {
+        self.drawing = true;
+        self.drawing_start = node;
+        self.drawing_edge = vec![pos];
+        self.stroke_grid = SegmentGrid::new(SEGMENT_GRID_CELL_SIZE);
+        self.spot_grid = self.index_spots();
+        self.leaving_start = true;
+    }
+
+    /// a grid of where every spot is
+    fn index_spots(&self) -> PointGrid {
+        let mut grid = PointGrid::new(SEGMENT_GRID_CELL_SIZE);
+        for (id, node) in self.nodes.iter() {
+            grid.insert(*id, node.pos);
         }
-        let mut a = self.drawing_edge.last().unwrap().clone();
-        if (a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y) < MIN_EDGE_SEGMENT_DISTANCE {
-            return false;
+        return grid;
+    }
+
+    fn add_to_edge(&mut self, b: Point) {
+        if let Some(a) = self.drawing_edge.last() {
+            self.stroke_grid.insert(*a, b);
+        }
+        let start_pos = self.nodes[&self.drawing_start].pos;
+        self.leaving_start &= Self::within_clearance(&start_pos, &b);
+        self.drawing_edge.push(b);
+    }
+
+    fn can_add_to_edge(&self, b: &Point) -> Result<(), MoveError> {
+        if self.drawing_edge.is_empty() {
+            return Ok(());
         }
-        // shift first point along direction of edge by unit amount, to avoid collision with sibling
-        // edges originating from same node
-        // TODO: this doesn't work very well. find another way. maybe edges should originate from edge of node
-        // and collision check with node should occur?
-        if self.drawing_edge.len() == 1 {
-            let mut dir_x = b.x - a.x;
-            if b.x - a.x > 0 {
-                dir_x /= (b.x - a.x).abs();
+        let a = *self.drawing_edge.last().unwrap();
+        for (c, d) in self.segment_grid.query(&a, b) {
+            if self.segments_clash(c, d, &a, b) {
+                return Err(MoveError::Crossing);
             }
-            let mut dir_y = b.y - a.y;
-            if b.y - a.y > 0 {
-                dir_y /= (b.y - a.y).abs();
+        }
+        // the edge can't cross itself either. The last segment is joined to ab so is skipped
+        let len = self.drawing_edge.len();
+        let last = (len >= 2).then(|| (self.drawing_edge[len - 2], a));
+        for (c, d) in self.stroke_grid.query(&a, b) {
+            if Some((*c, *d)) != last && self.segments_clash(c, d, &a, b) {
+                return Err(MoveError::Crossing);
             }
-            a.x += dir_x;
-            a.x += dir_y
         }
-        for segments in self.edges.iter() {
-            for i in 1..segments.len() {
-                if edges_intersect(&segments[i - 1], &segments[i], &a, b) {
-                    return false;
+        if let Some(node) = self.spot_in_way(&a, b) {
+            return Err(MoveError::PassesThroughSpot(node));
+        }
+        return Ok(());
+    }
+
+    /// checks a complete edge from start to end, before it is played
+    fn check_edge(&self, edge: &[Point], start: usize, end: usize) -> Result<(), MoveError> {
+        // an edge back to the same spot has to go round something
+        if end == start && edge.len() < 4 {
+            return Err(MoveError::TooShort);
+        }
+        // the edge's own segments, apart from the one joined to the segment being checked
+        let mut earlier = SegmentGrid::new(SEGMENT_GRID_CELL_SIZE);
+        let spots = self.index_spots();
+        let clearance = SPOT_CLEARANCE.ceil() as i32;
+        for i in 1..edge.len() {
+            let (a, b) = (&edge[i - 1], &edge[i]);
+            for (c, d) in self.segment_grid.query(a, b) {
+                if self.segments_clash(c, d, a, b) {
+                    return Err(MoveError::Crossing);
                 }
             }
+            for (c, d) in earlier.query(a, b) {
+                if self.segments_clash(c, d, a, b) {
+                    return Err(MoveError::Crossing);
+                }
+            }
+            for (id, pos) in spots.query(a, b, clearance) {
+                if id != start && id != end && segment_point_distance(a, b, &pos) < SPOT_CLEARANCE {
+                    return Err(MoveError::PassesThroughSpot(id));
+                }
+            }
+            if i >= 2 {
+                earlier.insert(edge[i - 2], edge[i - 1]);
+            }
         }
-        return true;
+        return Ok(());
     }
 
-    fn bisect_pos(edge: &Vec<Point>) -> Point {
-        // TODO: find a better method of finding the midpoint
-        return edge[edge.len() / 2];
+    /// whether two edge segments touch anywhere other than a spot they both connect to
+    fn segments_clash(&self, a: &Point, b: &Point, c: &Point, d: &Point) -> bool {
+        return match segment_intersection(a, b, c, d) {
+            Intersection::None => false,
+            Intersection::SharedEndpoint(p) => !self.is_node_centre(&p),
+            _ => true,
+        };
     }
 
-    fn refine_edge(edge: &mut Vec<Point>) {
-        // reduce line resolution by resampling at a fixed distance
-        const MIN_DISTANCE: i32 = 1000;
-        let mut i = 0;
-        loop {
-            if i >= edge.len() - 2 {
-                break;
+    /// finds a spot that the segment ab of the edge being drawn would pass through.
+    /// The segment may touch the start spot while the edge is still leaving it,
+    /// and any spot that b lies on, as the edge could end there
+    fn spot_in_way(&self, a: &Point, b: &Point) -> Option<usize> {
+        let clearance = SPOT_CLEARANCE.ceil() as i32;
+        for (id, pos) in self.spot_grid.query(a, b, clearance) {
+            if segment_point_distance(a, b, &pos) >= SPOT_CLEARANCE {
+                continue;
             }
-            let j = i + 1;
-            loop {
-                if j >= edge.len() - 2 {
-                    break;
-                }
-                let dist = Self::point_distance(&edge[i], &edge[j]);
-                if dist < MIN_DISTANCE {
-                    edge.remove(j);
-                } else {
-                    break;
-                }
+            if id == self.drawing_start && self.leaving_start {
+                continue;
             }
-            i = j;
-        }
-        // smoothing
-        // let iter = 3;
-        // for _n in 0..iter {
-        //     let mut i = 1;
-        //     loop {
-        //         if i >= edge.len() - 2 {
-        //             break;
-        //         }
-        //         // take two new points at 1/4 and 3/4 of each segment between i and i+1
-        //         // and replace i and i+1 with them.
-        //         let q = edge[i].mul(3).div(4) + edge[i + 1].div(4);
-        //         let p = edge[i].div(4) + edge[i + 1].mul(3).div(4);
-        //         println!("({},{}, ({},{})", q.x, q.y, p.x, p.y);
-        //         edge.insert(i + 1, q);
-        //         edge.insert(i + 2, p);
-        //         if i > 4 {
-        //             // remove old points from previous refinement
-        //             edge.remove(i - 3);
-        //         }
-        //         i += 3;
-        //     }
-        // }
+            if Self::within_clearance(&pos, b) {
+                continue;
+            }
+            return Some(id);
+        }
+        return None;
+    }
+
+    fn is_node_centre(&self, p: &Point) -> bool {
+        return self.nodes.values().any(|node| node.pos == *p);
+    }
+
+    fn within_clearance(node_pos: &Point, p: &Point) -> bool {
+        return (Self::point_distance(node_pos, p) as f32) < SPOT_CLEARANCE * SPOT_CLEARANCE;
     }
 
     /// calculates the euclidean distance between a and b
@@ -320,58 +1063,277 @@ impl UI {
         return (a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y);
     }
 
-    /// TODO: this is potentially broken by recent changes to the way edges are drawn
-    /// determines whether a point lies within a polygon defined by it's nodes and
-    /// the edges drawn between them, using the winding method
-    /// https://web.archive.org/web/20130126163405/http://geomalgorithms.com/a03-_inclusion.html
-    fn point_in_polygon(&self, point: &usize, polygon: &Vec<usize>) -> bool {
-        // winding number method
-        let mut wn = 0;
-        let point = &self.nodes[point].pos;
-        let polygon = polygon
-            .iter()
-            .map(|id| &self.nodes[id].pos)
-            .collect::<Vec<&Point>>();
-        for i in 0..polygon.len() - 1 {
-            let a = &polygon[i];
-            let b = &polygon[i + 1];
-            if a.y <= point.y {
-                if b.y > point.y {
-                    // upward crossing
-                    if Self::is_left(a, b, point) > 0 {
-                        wn += 1;
-                    }
-                }
+    /// determines whether a node lies within the face bounded by a walk around
+    /// the given edges, each followed forwards or backwards. The face is to the left
+    /// of the walk, so it is the inside of the outline if that goes round in order of
+    /// increasing angle, and the outside otherwise. Edges the game knows about but
+    /// that haven't been added to the board yet are passed in as `pending`
+    fn face_contains(&self, walk: &[(usize, bool)], node: usize, pending: &[&Vec<Point>]) -> bool {
+        return self.walk_contains(walk, &self.nodes[&node].pos, pending);
+    }
+
+    /// whether p is to the left of the walk; see face_contains
+    fn walk_contains(&self, walk: &[(usize, bool)], p: &Point, pending: &[&Vec<Point>]) -> bool {
+        let mut outline = Vec::new();
+        for (edge, forwards) in walk.iter() {
+            let points = match self.edges.get(*edge) {
+                Some(edge) => &edge.points,
+                None => pending[*edge - self.edges.len()],
+            };
+            // the end of each edge is the start of the next, so it is left out
+            if *forwards {
+                outline.extend(&points[..points.len() - 1]);
             } else {
-                if b.y <= point.y {
-                    // downward crossing
-                    if Self::is_left(a, b, point) < 0 {
-                        wn -= 1;
-                    }
-                }
+                outline.extend(points[1..].iter().rev());
             }
         }
-        println!("WN: {:?}", wn);
-        return wn != 0;
+        let inside = geometry::point_in_polygon(&outline, p);
+        return inside == (geometry::signed_area(&outline) > 0);
     }
+}
 
-    /// returns true if c is left of the line from a to b
-    /// See isLeft(): https://web.archive.org/web/20130406084141/http://geomalgorithms.com/a01-_area.html
-    fn is_left(a: &Point, b: &Point, c: &Point) -> i32 {
-        return (b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y);
+/// sets up a game and its board, replaying the saved game if there is one
+pub fn load_game(
+    saved_game: Option<&SavedGame>,
+    ui: impl FnOnce(&Game) -> UI,
+) -> Result<(Game, UI), String> {
+    // spots removed in a sandbox leave gaps in the ids, which are filled in and removed again
+    let spots = saved_game.map_or(NEW_GAME_SPOTS, |saved| {
+        return saved.spots.iter().map(|(id, _)| id + 1).max().unwrap_or(0);
+    });
+    let player1 = Player::new(0, String::from("player 1"));
+    let player2 = Player::new(1, String::from("player 2"));
+    let mut game = Game::new(player1, player2, spots);
+    let mut ui = ui(&game);
+    if let Some(saved) = saved_game {
+        ui.load(saved, &mut game)?;
     }
+    return Ok((game, ui));
+}
 
-    /// determines the bounding box of these points
-    /// this is different to the polygon length, as it doesn't account
-    /// for edges, just the smalest box that can fit around the points
-    /// Its used to check whether a loop in the graph could fit inside
-    /// another one, when finding the smallest enclosing polygon of a point
-    fn point_bounds(&self, nodes: &Vec<usize>) -> Rect {
-        let points_vec = nodes
-            .iter()
-            .map(|n| self.nodes.get(n).unwrap().pos)
-            .collect::<Vec<Point>>();
-        let points = points_vec.as_slice().try_into().unwrap();
-        return Rect::from_enclose_points(points, None).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::{load_game, BOARD_SIZE, UI};
+    use crate::logic::game::{Game, MoveError};
+    use crate::view::saved_game::SavedGame;
+    use crate::view::svg::SvgRenderer;
+    use sdl2::event::Event;
+    use sdl2::keyboard::{Keycode, Mod};
+    use sdl2::mouse::{MouseButton, MouseState};
+    use sdl2::rect::Point;
+
+    /// a game and its board, set up from a saved game
+    fn board(saved: &str) -> (Game, UI) {
+        let saved = SavedGame::parse(saved).unwrap();
+        return load_game(Some(&saved), |game| {
+            let renderer = SvgRenderer::new(BOARD_SIZE.0, BOARD_SIZE.1);
+            return UI::new(Box::new(renderer), game.get_nodes());
+        })
+        .unwrap();
+    }
+
+    fn press(x: i32, y: i32, mouse_btn: MouseButton) -> Event {
+        return Event::MouseButtonDown {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mouse_btn,
+            clicks: 1,
+            x,
+            y,
+        };
+    }
+
+    fn release(x: i32, y: i32, mouse_btn: MouseButton) -> Event {
+        return Event::MouseButtonUp {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mouse_btn,
+            clicks: 1,
+            x,
+            y,
+        };
+    }
+
+    fn motion(x: i32, y: i32) -> Event {
+        return Event::MouseMotion {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mousestate: MouseState::from_sdl_state(0),
+            x,
+            y,
+            xrel: 0,
+            yrel: 0,
+        };
+    }
+
+    fn key(keycode: Keycode) -> Event {
+        return Event::KeyDown {
+            timestamp: 0,
+            window_id: 0,
+            keycode: Some(keycode),
+            scancode: None,
+            keymod: Mod::NOMOD,
+            repeat: false,
+        };
+    }
+
+    fn points(points: &[(i32, i32)]) -> Vec<Point> {
+        return points.iter().map(|&(x, y)| Point::new(x, y)).collect();
+    }
+
+    fn toast(ui: &UI) -> Option<&str> {
+        return ui.toast.as_ref().map(|(message, _)| message.as_str());
+    }
+
+    #[test]
+    fn test_load_game_with_gaps() {
+        // spot 1 was removed in a sandbox before this was saved
+        let (game, _) = board("spot 0 200 300\nspot 2 600 300\nmove 0 2 200,300 400,320 600,300");
+        assert_eq!(game.get_nodes(), vec![0, 2, 3]);
+        assert_eq!(game.edge_ends(0), (0, 3));
+    }
+
+    #[test]
+    fn test_stroke_through_spot() {
+        let (mut game, mut ui) = board("spot 0 200 300\nspot 1 400 300\nspot 2 600 300");
+        let path = points(&[(200, 300), (300, 300), (500, 300), (600, 300)]);
+        assert_eq!(
+            ui.play_edge(0, 2, path, &mut game),
+            Err(MoveError::PassesThroughSpot(1))
+        );
+        assert!(ui.edges.is_empty());
+        assert_eq!(game.get_nodes(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_stroke_crossing_edge() {
+        let (mut game, mut ui) = board(
+            "spot 0 200 200\nspot 1 200 400\nspot 2 100 300\nspot 3 300 300\n\
+             edge 0 1 200,200 200,300 200,400",
+        );
+        let path = points(&[(100, 300), (150, 300), (250, 300), (300, 300)]);
+        assert_eq!(
+            ui.play_edge(2, 3, path, &mut game),
+            Err(MoveError::Crossing)
+        );
+        assert_eq!(ui.edges.len(), 1);
+    }
+
+    #[test]
+    fn test_stroke_crossing_itself() {
+        let (mut game, mut ui) = board("spot 0 300 300\nspot 1 200 200");
+        // goes right, then down and round, back up across its own start
+        let path = points(&[
+            (300, 300),
+            (500, 300),
+            (500, 400),
+            (400, 400),
+            (400, 200),
+            (200, 200),
+        ]);
+        assert_eq!(
+            ui.play_edge(0, 1, path, &mut game),
+            Err(MoveError::Crossing)
+        );
+        assert!(ui.edges.is_empty());
+    }
+
+    #[test]
+    fn test_toast_on_illegal_stroke() {
+        let (mut game, mut ui) = board("spot 0 200 300\nspot 1 400 300\nspot 2 600 300");
+        assert_eq!(toast(&ui), None);
+        ui.process(press(200, 300, MouseButton::Left), &mut game);
+        ui.process(motion(300, 300), &mut game);
+        ui.process(motion(500, 300), &mut game);
+        let message = MoveError::PassesThroughSpot(1).to_string();
+        assert_eq!(toast(&ui), Some(message.as_str()));
+        ui.process(release(600, 300, MouseButton::Left), &mut game);
+        assert_eq!(toast(&ui), Some(message.as_str()));
+        assert!(ui.edges.is_empty());
+    }
+
+    #[test]
+    fn test_setup_places_and_drags_spots() {
+        let (mut game, mut ui) = board("spot 0 200 300");
+        ui.start_setup();
+        ui.process(press(400, 300, MouseButton::Left), &mut game);
+        ui.process(release(400, 300, MouseButton::Left), &mut game);
+        assert_eq!(game.get_nodes(), vec![0, 1]);
+        assert_eq!(ui.nodes[&1].pos, Point::new(400, 300));
+
+        // too close to the new spot
+        ui.process(press(430, 300, MouseButton::Left), &mut game);
+        assert_eq!(toast(&ui), Some("Spots need space around them"));
+        assert_eq!(game.get_nodes(), vec![0, 1]);
+        ui.process(release(430, 300, MouseButton::Left), &mut game);
+
+        ui.process(press(200, 300, MouseButton::Left), &mut game);
+        ui.process(motion(250, 350), &mut game);
+        // spot 0 stays where it was rather than landing on spot 1
+        ui.process(motion(400, 300), &mut game);
+        ui.process(release(400, 300, MouseButton::Left), &mut game);
+        assert_eq!(ui.nodes[&0].pos, Point::new(250, 350));
+        assert_eq!(
+            ui.history.spots,
+            vec![(0, Point::new(250, 350)), (1, Point::new(400, 300))]
+        );
+    }
+
+    #[test]
+    fn test_sandbox_edges_and_removal() {
+        let (mut game, mut ui) = board(
+            "spot 0 200 300\nspot 1 600 300\nspot 2 400 100\nedge 0 1 200,300 400,300 600,300",
+        );
+        ui.process(key(Keycode::B), &mut game);
+
+        // edges drawn in the sandbox don't get a spot
+        ui.process(press(400, 100, MouseButton::Left), &mut game);
+        for (x, y) in [(450, 150), (500, 200), (550, 250)] {
+            ui.process(motion(x, y), &mut game);
+        }
+        ui.process(release(600, 300, MouseButton::Left), &mut game);
+        assert_eq!(ui.edges.len(), 2);
+        assert_eq!(game.get_nodes(), vec![0, 1, 2]);
+        assert_eq!(game.edge_ends(1), (2, 1));
+
+        ui.process(press(200, 300, MouseButton::Right), &mut game);
+        assert_eq!(
+            toast(&ui),
+            Some(MoveError::HasEdges(0).to_string().as_str())
+        );
+        assert_eq!(game.get_nodes(), vec![0, 1, 2]);
+
+        ui.process(press(400, 300, MouseButton::Right), &mut game);
+        assert_eq!(ui.edges.len(), 1);
+        assert_eq!(game.edge_ends(0), (2, 1));
+        ui.process(press(200, 300, MouseButton::Right), &mut game);
+        assert_eq!(game.get_nodes(), vec![1, 2]);
+        assert_eq!(ui.history.spots.len(), 2);
+        assert_eq!(ui.history.edges.len(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_board() {
+        use super::{UIEdge, UINode};
+
+        let mut node = UINode::at_position(Point::new(200, 300));
+        node.focused = true;
+        let json = serde_json::to_string(&node).unwrap();
+        assert_eq!(json, r#"{"pos":[200,300]}"#);
+        let copy: UINode = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.pos, node.pos);
+        assert!(!copy.focused);
+
+        let edge = UIEdge {
+            points: vec![Point::new(200, 300), Point::new(400, 320)],
+        };
+        let json = serde_json::to_string(&edge).unwrap();
+        assert_eq!(json, r#"{"points":[[200,300],[400,320]]}"#);
+        let copy: UIEdge = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.points, edge.points);
     }
 }
@@ -1,115 +1,251 @@
 use std::cmp::{max, min};
 
-use sdl2::pixels::Color;
-use sdl2::rect::{Point, Rect};
-use sdl2::render::{Canvas, TextureQuery};
-use sdl2::ttf::Font;
-use sdl2::video::Window;
-
-pub trait TextRendering {
-    fn render_text(&mut self, font: &Font, text: String, position: Point, color: Color);
+use sdl2::rect::Point;
+
+/// how two segments meet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Intersection {
+    /// the segments don't meet
+    None,
+    /// the segments meet only at an endpoint they have in common
+    SharedEndpoint(Point),
+    /// the segments meet at a single point which isn't a shared endpoint
+    Crossing,
+    /// the segments are collinear and overlap along some length
+    Overlap,
 }
 
-impl TextRendering for Canvas<Window> {
-    fn render_text(&mut self, font: &Font, text: String, position: Point, color: Color) {
-        // render a surface, and convert it to a texture bound to the canvas
-        let surface = font
-            .render(&text)
-            .blended(Color::RGBA(255, 0, 0, 255))
-            .map_err(|e| e.to_string())
-            .unwrap();
-        let texture_creator = self.texture_creator();
-        let texture = texture_creator
-            .create_texture_from_surface(&surface)
-            .map_err(|e| e.to_string())
-            .unwrap();
-
-        self.set_draw_color(color);
-
-        let TextureQuery { width, height, .. } = texture.query();
-
-        let target = Rect::from_center(position, width, height);
-
-        self.copy(&texture, None, Some(target))
-            .map_err(|e| e.to_string())
-            .unwrap();
-    }
+/// the sign of the area of the triangle abc: 1 if c is left of the line from
+/// a to b, -1 if right and 0 if collinear. See isLeft():
+/// https://web.archive.org/web/20130406084141/http://geomalgorithms.com/a01-_area.html
+pub fn orientation(a: &Point, b: &Point, c: &Point) -> i64 {
+    let (a, b, c) = (
+        (a.x as i64, a.y as i64),
+        (b.x as i64, b.y as i64),
+        (c.x as i64, c.y as i64),
+    );
+    return ((b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1)).signum();
 }
 
-/// checks whether edge ab intersects with edge cd
-pub fn edges_intersect(a: &Point, b: &Point, c: &Point, d: &Point) -> bool {
-    const BOUNDS_FUDGE_FACTOR: i32 = 2;
+/// whether p, known to be collinear with ab, lies on the segment ab
+fn on_segment(a: &Point, b: &Point, p: &Point) -> bool {
+    return p.x >= min(a.x, b.x)
+        && p.x <= max(a.x, b.x)
+        && p.y >= min(a.y, b.y)
+        && p.y <= max(a.y, b.y);
+}
 
-    let m_ab = (a.y - b.y) as f32 / (a.x - b.x) as f32;
-    let m_cd = (c.y - d.y) as f32 / (c.x - d.x) as f32;
+/// works out how segment ab meets segment cd, using exact integer arithmetic
+pub fn segment_intersection(a: &Point, b: &Point, c: &Point, d: &Point) -> Intersection {
+    let o1 = orientation(a, b, c);
+    let o2 = orientation(a, b, d);
+    let o3 = orientation(c, d, a);
+    let o4 = orientation(c, d, b);
 
-    // parallel
-    if m_ab == m_cd {
-        return false;
+    if o1 == 0 && o2 == 0 && o3 == 0 && o4 == 0 {
+        return collinear_intersection(a, b, c, d);
     }
 
-    let p: Point;
-
-    // Equation of a straight line won't work with infinite gradient.
-    // I could use the cross product for line segment intersection, but wanted
-    // to do it from first principles
-    if m_ab.is_infinite() {
-        let c_cd = c.y as f32 - m_cd * c.x as f32;
-        p = Point::new(a.x, (m_cd * a.x as f32 + c_cd) as i32);
-    } else if m_cd.is_infinite() {
-        let c_ab = a.y as f32 - m_ab * a.x as f32;
-        p = Point::new(c.x, (m_ab * c.x as f32 + c_ab) as i32);
-    } else {
-        let c_ab = a.y as f32 - m_ab * a.x as f32;
-        let c_cd = c.y as f32 - m_cd * c.x as f32;
-
-        // intersection point
-        p = Point::new(
-            ((c_cd - c_ab) / (m_ab - m_cd)) as i32,
-            ((m_cd * c_ab - m_ab * c_cd) / (m_cd - m_ab)) as i32,
-        );
+    let meet = (o1 * o2 < 0 && o3 * o4 < 0)
+        || (o1 == 0 && on_segment(a, b, c))
+        || (o2 == 0 && on_segment(a, b, d))
+        || (o3 == 0 && on_segment(c, d, a))
+        || (o4 == 0 && on_segment(c, d, b));
+    if !meet {
+        return Intersection::None;
+    }
+    // segments that aren't collinear can only meet once,
+    // so if they share an endpoint that must be where they meet
+    for p in [a, b] {
+        if p == c || p == d {
+            return Intersection::SharedEndpoint(*p);
+        }
     }
+    return Intersection::Crossing;
+}
 
-    if p.x > min(a.x, b.x) - BOUNDS_FUDGE_FACTOR
-        && p.x < max(a.x, b.x) + BOUNDS_FUDGE_FACTOR
-        && p.x > min(c.x, d.x) - BOUNDS_FUDGE_FACTOR
-        && p.x < max(c.x, d.x) + BOUNDS_FUDGE_FACTOR
-        && p.y > min(a.y, b.y) - BOUNDS_FUDGE_FACTOR
-        && p.y < max(a.y, b.y) + BOUNDS_FUDGE_FACTOR
-        && p.y > min(c.y, d.y) - BOUNDS_FUDGE_FACTOR
-        && p.y < max(c.y, d.y) + BOUNDS_FUDGE_FACTOR
-    {
-        return true;
+/// works out how ab meets cd when all four points lie on one line
+fn collinear_intersection(a: &Point, b: &Point, c: &Point, d: &Point) -> Intersection {
+    // project onto whichever axis the line isn't perpendicular to
+    let vertical = a.x == b.x && b.x == c.x && c.x == d.x;
+    let project = |p: &Point| if vertical { p.y } else { p.x };
+    let lo = max(min(project(a), project(b)), min(project(c), project(d)));
+    let hi = min(max(project(a), project(b)), max(project(c), project(d)));
+    if lo > hi {
+        return Intersection::None;
+    }
+    if lo < hi {
+        return Intersection::Overlap;
+    }
+    // the segments touch at a single point, which must be one of the endpoints
+    let p = *[a, b, c, d].into_iter().find(|p| project(p) == lo).unwrap();
+    if (p == *a || p == *b) && (p == *c || p == *d) {
+        return Intersection::SharedEndpoint(p);
     }
+    return Intersection::Crossing;
+}
 
-    return false;
+/// calculates the shortest distance from point p to the segment ab
+pub fn segment_point_distance(a: &Point, b: &Point, p: &Point) -> f32 {
+    let (abx, aby) = ((b.x - a.x) as f32, (b.y - a.y) as f32);
+    let (apx, apy) = ((p.x - a.x) as f32, (p.y - a.y) as f32);
+    let length_sq = abx * abx + aby * aby;
+    // position of the closest point along ab, as a fraction of its length
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        ((apx * abx + apy * aby) / length_sq).clamp(0.0, 1.0)
+    };
+    let (dx, dy) = (apx - t * abx, apy - t * aby);
+    return (dx * dx + dy * dy).sqrt();
 }
 
 #[cfg(test)]
 mod tests {
-    use super::edges_intersect;
+    use super::{segment_intersection, segment_point_distance, Intersection};
+    use proptest::prelude::*;
     use sdl2::rect::Point;
+    use std::collections::HashSet;
+
+    // coordinates for the property tests are kept small so the reference stays cheap
+    const MAX_COORD: i32 = 4;
+    // the largest denominator an intersection parameter can have for those coordinates
+    const MAX_DENOMINATOR: i64 = 2 * MAX_COORD as i64 * MAX_COORD as i64;
+
+    fn gcd(a: i64, b: i64) -> i64 {
+        return if b == 0 { a } else { gcd(b, a % b) };
+    }
+
+    /// brute force version of segment_intersection. Every rational point on ab with a
+    /// denominator up to MAX_DENOMINATOR is tested against cd, which is enough to find
+    /// every point the segments have in common (as long as the coordinates are small).
+    /// Points are kept as reduced fractions so they can be compared exactly
+    fn reference_intersection(a: &Point, b: &Point, c: &Point, d: &Point) -> Intersection {
+        let (ax, ay, bx, by) = (a.x as i64, a.y as i64, b.x as i64, b.y as i64);
+        let (cx, cy, dx, dy) = (c.x as i64, c.y as i64, d.x as i64, d.y as i64);
+        let mut common = HashSet::new();
+        for q in 1..=MAX_DENOMINATOR {
+            for k in 0..=q {
+                // the point a + (b - a) * k / q, multiplied by q
+                let px = ax * q + (bx - ax) * k;
+                let py = ay * q + (by - ay) * k;
+                let collinear = (dx - cx) * (py - cy * q) - (dy - cy) * (px - cx * q) == 0;
+                let within = px >= cx.min(dx) * q
+                    && px <= cx.max(dx) * q
+                    && py >= cy.min(dy) * q
+                    && py <= cy.max(dy) * q;
+                if collinear && within {
+                    let g = gcd(gcd(px.abs(), py.abs()), q);
+                    common.insert((px / g, py / g, q / g));
+                }
+            }
+        }
+        if common.is_empty() {
+            return Intersection::None;
+        }
+        if common.len() > 1 {
+            return Intersection::Overlap;
+        }
+        let p = common.into_iter().next().unwrap();
+        let fraction = |p: &Point| (p.x as i64, p.y as i64, 1);
+        if (p == fraction(a) || p == fraction(b)) && (p == fraction(c) || p == fraction(d)) {
+            return Intersection::SharedEndpoint(Point::new(p.0 as i32, p.1 as i32));
+        }
+        return Intersection::Crossing;
+    }
+
+    fn point() -> impl Strategy<Value = Point> {
+        return (0..=MAX_COORD, 0..=MAX_COORD).prop_map(|(x, y)| Point::new(x, y));
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn prop_intersection_matches_reference(a in point(), b in point(), c in point(), d in point()) {
+            prop_assert_eq!(segment_intersection(&a, &b, &c, &d), reference_intersection(&a, &b, &c, &d));
+        }
+
+        #[test]
+        fn prop_intersection_is_symmetric(a in point(), b in point(), c in point(), d in point()) {
+            let result = segment_intersection(&a, &b, &c, &d);
+            prop_assert_eq!(segment_intersection(&c, &d, &a, &b), result);
+            prop_assert_eq!(segment_intersection(&b, &a, &d, &c), result);
+        }
+    }
+
+    #[test]
+    fn test_segment_intersection() {
+        let p = |x, y| Point::new(x, y);
+        // crossing
+        assert_eq!(
+            segment_intersection(&p(0, 0), &p(4, 4), &p(0, 4), &p(4, 0)),
+            Intersection::Crossing
+        );
+        // end of one segment touching the middle of the other
+        assert_eq!(
+            segment_intersection(&p(0, 0), &p(4, 0), &p(2, 0), &p(2, 3)),
+            Intersection::Crossing
+        );
+        // edges leaving the same spot
+        assert_eq!(
+            segment_intersection(&p(0, 0), &p(4, 0), &p(0, 0), &p(0, 4)),
+            Intersection::SharedEndpoint(p(0, 0))
+        );
+        // collinear and joined end to end
+        assert_eq!(
+            segment_intersection(&p(0, 0), &p(2, 2), &p(2, 2), &p(4, 4)),
+            Intersection::SharedEndpoint(p(2, 2))
+        );
+        // collinear and overlapping
+        assert_eq!(
+            segment_intersection(&p(0, 0), &p(3, 0), &p(2, 0), &p(5, 0)),
+            Intersection::Overlap
+        );
+        // collinear but apart
+        assert_eq!(
+            segment_intersection(&p(0, 0), &p(0, 1), &p(0, 2), &p(0, 5)),
+            Intersection::None
+        );
+        // parallel
+        assert_eq!(
+            segment_intersection(&p(0, 0), &p(4, 0), &p(0, 1), &p(4, 1)),
+            Intersection::None
+        );
+    }
 
     #[test]
     fn test_intersect() {
         assert_eq!(
-            edges_intersect(
+            segment_intersection(
                 &Point::new(100, 100),
                 &Point::new(500, 400),
                 &Point::new(400, 0),
                 &Point::new(200, 700),
             ),
-            true
+            Intersection::Crossing
         );
         // with vertical line
         assert_eq!(
-            edges_intersect(
+            segment_intersection(
                 &Point::new(20, 10),
                 &Point::new(20, 40),
                 &Point::new(10, 20),
                 &Point::new(30, 30),
             ),
-            true
+            Intersection::Crossing
         );
     }
+
+    #[test]
+    fn test_segment_point_distance() {
+        let a = Point::new(0, 0);
+        let b = Point::new(10, 0);
+        // closest to the middle of the segment
+        assert_eq!(segment_point_distance(&a, &b, &Point::new(5, 4)), 4.0);
+        // closest to an end
+        assert_eq!(segment_point_distance(&a, &b, &Point::new(13, 4)), 5.0);
+        // degenerate segment
+        assert_eq!(segment_point_distance(&a, &a, &Point::new(3, 4)), 5.0);
+    }
 }